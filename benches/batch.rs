@@ -0,0 +1,55 @@
+// Copyright (c) 2017 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+
+//! Benchmarks for validating batches of `Digest`s, sequentially and in parallel.
+//!
+//! Run via `cargo bench --bench batch --features rayon`. The throughput of the parallel variant
+//! should scale with the batch size up to the number of logical cores.
+
+#[macro_use]
+extern crate criterion;
+extern crate guardhaus;
+
+use criterion::{BenchmarkId, Criterion, Throughput, black_box};
+use fixtures::{PASSWORD, rfc2617_digest};
+use guardhaus::types::HashAlgorithm;
+use guardhaus::validator::{BatchRequest, validate_batch, validate_batch_parallel};
+
+mod fixtures;
+
+fn batch(c: &mut Criterion) {
+    let digest = rfc2617_digest(HashAlgorithm::SHA256);
+    let mut group = c.benchmark_group("validate_batch");
+    for &size in &[1, 4, 8] {
+        let requests: Vec<BatchRequest> = (0..size)
+            .map(|_| (digest.clone(), "GET", &b""[..], digest.request_uri.clone()))
+            .collect();
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_with_input(BenchmarkId::new("sequential", size), &requests, |b, requests| {
+            b.iter(|| validate_batch(black_box(requests), black_box(PASSWORD)))
+        });
+        group.bench_with_input(BenchmarkId::new("parallel", size), &requests, |b, requests| {
+            b.iter(|| validate_batch_parallel(black_box(requests), black_box(PASSWORD)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, batch);
+criterion_main!(benches);
@@ -0,0 +1,192 @@
+// Copyright (c) 2017 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+
+//! End-to-end benchmarks for the operations performed on every authenticated request: parsing,
+//! generating, validating, and serializing `Digest` headers.
+//!
+//! Run via `cargo bench --bench digest_auth`. Each benchmark takes criterion's default of 100
+//! samples. Baseline numbers are recorded in `BENCHMARKS.md`.
+
+#[macro_use]
+extern crate criterion;
+extern crate guardhaus;
+
+use criterion::{Criterion, black_box};
+use fixtures::{PASSWORD, RFC2617_HEADER, RFC7616_HEADER, rfc2617_digest};
+use guardhaus::digest::{Digest, Username};
+use guardhaus::hyper::header::Scheme;
+use guardhaus::types::HashAlgorithm;
+use std::fmt;
+use std::fmt::Write;
+
+mod fixtures;
+
+// Formats the header parameters via `Scheme::fmt_scheme`, as hyper does when serializing an
+// `Authorization<Digest>` header.
+struct FmtScheme<'a>(&'a Digest);
+
+impl<'a> fmt::Display for FmtScheme<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt_scheme(f)
+    }
+}
+
+fn bench_parse(c: &mut Criterion, name: &str, header: &str) {
+    c.bench_function(name, |b| {
+        b.iter(|| black_box(header).parse::<Digest>().expect("Could not parse header"))
+    });
+}
+
+fn bench_parse_header(c: &mut Criterion) {
+    bench_parse(c, "bench_parse_header", RFC2617_HEADER)
+}
+
+fn bench_parse_header_rfc7616(c: &mut Criterion) {
+    bench_parse(c, "bench_parse_header_rfc7616", RFC7616_HEADER)
+}
+
+fn bench_generate_response(c: &mut Criterion, name: &str, algorithm: HashAlgorithm) {
+    let digest = rfc2617_digest(algorithm);
+    c.bench_function(name, |b| {
+        b.iter(|| {
+            black_box(&digest).using_password("GET", b"", black_box(PASSWORD).to_owned())
+        })
+    });
+}
+
+#[allow(deprecated)]
+fn bench_generate_response_md5(c: &mut Criterion) {
+    bench_generate_response(c, "bench_generate_response_md5", HashAlgorithm::MD5)
+}
+
+fn bench_generate_response_sha256(c: &mut Criterion) {
+    bench_generate_response(c, "bench_generate_response_sha256", HashAlgorithm::SHA256)
+}
+
+fn bench_generate_response_sha512256(c: &mut Criterion) {
+    bench_generate_response(c, "bench_generate_response_sha512256", HashAlgorithm::SHA512256)
+}
+
+fn bench_validate(c: &mut Criterion, name: &str, algorithm: HashAlgorithm) {
+    let digest = rfc2617_digest(algorithm);
+    c.bench_function(name, |b| {
+        b.iter(|| {
+            black_box(&digest).validate_using_password(
+                "GET",
+                b"",
+                black_box(PASSWORD).to_owned(),
+            )
+        })
+    });
+}
+
+#[allow(deprecated)]
+fn bench_validate_md5(c: &mut Criterion) {
+    bench_validate(c, "bench_validate_md5", HashAlgorithm::MD5)
+}
+
+#[allow(deprecated)]
+fn bench_validate_md5_sess(c: &mut Criterion) {
+    bench_validate(c, "bench_validate_md5_sess", HashAlgorithm::MD5Session)
+}
+
+fn bench_validate_sha256(c: &mut Criterion) {
+    bench_validate(c, "bench_validate_sha256", HashAlgorithm::SHA256)
+}
+
+#[allow(deprecated)]
+fn bench_using_hashed_a1_md5(c: &mut Criterion) {
+    let digest = rfc2617_digest(HashAlgorithm::MD5);
+    c.bench_function("bench_using_hashed_a1_md5", |b| {
+        b.iter(|| {
+            black_box(&digest).using_hashed_a1(
+                "GET",
+                b"",
+                black_box("939e7578ed9e3c518a452acee763bce9").to_owned(),
+            )
+        })
+    });
+}
+
+#[allow(deprecated)]
+fn bench_fmt_scheme(c: &mut Criterion) {
+    let digest = rfc2617_digest(HashAlgorithm::MD5);
+    let mut output = String::with_capacity(1024);
+    c.bench_function("bench_fmt_scheme", |b| {
+        b.iter(|| {
+            output.clear();
+            write!(output, "{}", FmtScheme(black_box(&digest))).expect("Could not format header");
+        })
+    });
+}
+
+fn bench_generate_userhash(c: &mut Criterion, name: &str, algorithm: HashAlgorithm, user: &str) {
+    let username = Username::Plain(user.to_owned());
+    c.bench_function(name, |b| {
+        b.iter(|| {
+            Digest::userhash(&algorithm, black_box(&username), black_box("testrealm@host.com"))
+        })
+    });
+}
+
+#[allow(deprecated)]
+fn bench_generate_userhash_md5(c: &mut Criterion) {
+    bench_generate_userhash(c, "bench_generate_userhash_md5", HashAlgorithm::MD5, "Mufasa")
+}
+
+fn bench_generate_userhash_sha256(c: &mut Criterion) {
+    bench_generate_userhash(c, "bench_generate_userhash_sha256", HashAlgorithm::SHA256, "Mufasa")
+}
+
+fn bench_generate_userhash_sha512256(c: &mut Criterion) {
+    bench_generate_userhash(
+        c,
+        "bench_generate_userhash_sha512256",
+        HashAlgorithm::SHA512256,
+        "Mufasa",
+    )
+}
+
+fn bench_generate_userhash_utf8_sha512256(c: &mut Criterion) {
+    bench_generate_userhash(
+        c,
+        "bench_generate_userhash_utf8_sha512256",
+        HashAlgorithm::SHA512256,
+        "J\u{e4}s\u{f8}n Doe",
+    )
+}
+
+criterion_group!(
+    digest_auth,
+    bench_parse_header,
+    bench_parse_header_rfc7616,
+    bench_generate_response_md5,
+    bench_generate_response_sha256,
+    bench_generate_response_sha512256,
+    bench_validate_md5,
+    bench_validate_md5_sess,
+    bench_validate_sha256,
+    bench_using_hashed_a1_md5,
+    bench_fmt_scheme,
+    bench_generate_userhash_md5,
+    bench_generate_userhash_sha256,
+    bench_generate_userhash_sha512256,
+    bench_generate_userhash_utf8_sha512256
+);
+criterion_main!(digest_auth);
@@ -0,0 +1,74 @@
+// Copyright (c) 2017 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+
+
+//! The `Digest` headers shared by the benchmarks.
+
+// Not every benchmark uses every fixture.
+#![allow(dead_code)]
+
+use guardhaus::digest::{Digest, Username};
+use guardhaus::types::{HashAlgorithm, NonceCount, Qop};
+
+pub const PASSWORD: &str = "Circle Of Life";
+
+// See: RFC 2617, Section 3.5
+pub const RFC2617_HEADER: &str = "username=\"Mufasa\", realm=\"testrealm@host.com\", \
+                                  nonce=\"dcd98b7102dd2f0e8b11d0f600bfb0c093\", \
+                                  uri=\"/dir/index.html\", qop=auth, nc=00000001, \
+                                  cnonce=\"0a4f113b\", \
+                                  response=\"6629fae49393a05397450978507c4ef1\", \
+                                  opaque=\"5ccc069c403ebaf9f0171e9517f40e41\"";
+
+// See: RFC 7616, Section 3.9.2
+pub const RFC7616_HEADER: &str = "username*=UTF-8''J%C3%A4s%C3%B8n%20Doe, \
+                                  realm=\"api@example.org\", uri=\"/doe.json\", \
+                                  algorithm=SHA-512-256, \
+                                  nonce=\"5TsQWLVdgBdmrQ0XsxbDODV+57QdFR34I9HAbC/RVvkK\", \
+                                  nc=00000001, \
+                                  cnonce=\"NTg6RKcb9boFIAS3KrFK9BGeh+iDa/sm6jUMp2wds69v\", \
+                                  qop=auth, \
+                                  response=\"ae66e67d6b427bd3f120414a82e4acff\
+                                  38e8ecd9101d6c861229025f607a79dd\", \
+                                  opaque=\"HRPCssKJSGjCrkzDg8OhwpzCiGPChXYjwrI2QmXDnsOS\", \
+                                  userhash=false";
+
+// See: RFC 2617, Section 3.5. The response is only valid for MD5, so it is regenerated for the
+// other algorithms.
+pub fn rfc2617_digest(algorithm: HashAlgorithm) -> Digest {
+    let mut digest = Digest {
+        username: Username::Plain("Mufasa".to_owned()),
+        realm: "testrealm@host.com".to_owned(),
+        nonce: "dcd98b7102dd2f0e8b11d0f600bfb0c093".to_owned(),
+        nonce_count: Some(NonceCount(1)),
+        response: String::new(),
+        request_uri: "/dir/index.html".to_owned(),
+        algorithm,
+        qop: Some(Qop::Auth),
+        client_nonce: Some("0a4f113b".to_owned()),
+        opaque: Some("5ccc069c403ebaf9f0171e9517f40e41".to_owned()),
+        charset: None,
+        userhash: false,
+        method: None,
+    };
+    digest.response = digest
+        .using_password("GET", b"", PASSWORD.to_owned())
+        .expect("Could not generate response");
+    digest
+}
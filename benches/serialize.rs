@@ -0,0 +1,98 @@
+// Copyright (c) 2017 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Counts the heap allocations made while serializing a `Digest` header.
+//!
+//! Run via `cargo bench --bench serialize`.
+
+extern crate guardhaus;
+
+use guardhaus::digest::{Digest, Username};
+use guardhaus::hyper::header::{Authorization, Charset};
+use guardhaus::types::{HashAlgorithm, NonceCount, Qop};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+const ITERATIONS: usize = 1_000_000;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+// See: RFC 7616, Section 3.9.2
+fn rfc7616_sha512_256_header() -> Authorization<Digest> {
+    Authorization(Digest {
+        username: Username::Plain(
+            "488869477bf257147b804c45308cd62ac4e25eb717b12b298c79e62dcea254ec".to_owned(),
+        ),
+        realm: "api@example.org".to_owned(),
+        nonce: "5TsQWLVdgBdmrQ0XsxbDODV+57QdFR34I9HAbC/RVvkK".to_owned(),
+        nonce_count: Some(NonceCount(1)),
+        response: "ae66e67d6b427bd3f120414a82e4acff38e8ecd9101d6c861229025f607a79dd".to_owned(),
+        request_uri: "/doe.json".to_owned(),
+        algorithm: HashAlgorithm::SHA512256,
+        qop: Some(Qop::Auth),
+        client_nonce: Some("NTg6RKcb9boFIAS3KrFK9BGeh+iDa/sm6jUMp2wds69v".to_owned()),
+        opaque: Some("HRPCssKJSGjCrkzDg8OhwpzCiGPChXYjwrI2QmXDnsOS".to_owned()),
+        charset: Some(Charset::Ext("UTF-8".to_owned())),
+        userhash: true,
+        method: None,
+    })
+}
+
+fn main() {
+    let header = rfc7616_sha512_256_header();
+    let mut output = String::with_capacity(1024);
+    let start = Instant::now();
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..ITERATIONS {
+        output.clear();
+        write!(output, "{}", header).expect("Could not serialize header");
+    }
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    let elapsed = start.elapsed();
+
+    println!(
+        "serialize/rfc7616: {} allocations per header, {:?} per header",
+        allocations as f64 / ITERATIONS as f64,
+        elapsed / ITERATIONS as u32
+    );
+}
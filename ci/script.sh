@@ -5,9 +5,23 @@ set -e
 
 travis-cargo build -- --target $TARGET
 travis-cargo test -- --target $TARGET
+travis-cargo test -- --target $TARGET --no-default-features --features ring-backend,std
+travis-cargo test -- --target $TARGET --no-default-features --features pure-rust-backend,std
+travis-cargo test -- --target $TARGET --features debug-validation
+travis-cargo test -- --target $TARGET --features http
+travis-cargo test -- --target $TARGET --features hyper1
+travis-cargo test -- --target $TARGET --features allow-md5
+travis-cargo test -- --target $TARGET --features axum
+travis-cargo test -- --target $TARGET --features tower
+travis-cargo test -- --target $TARGET --features reqwest-middleware
+travis-cargo test -- --target $TARGET --features saslprep
+travis-cargo test -- --target $TARGET --lib --no-default-features --features wasm
 
 if test "$TRAVIS_OS_NAME" = "linux" -a "$TARGET" = "x86_64-unknown-linux-gnu"; then
     travis-cargo --only stable doc
+    # no_std build
+    rustup target add thumbv7em-none-eabihf
+    cargo build --target thumbv7em-none-eabihf --no-default-features --features pure-rust-backend
 fi
 
 if test "$TRAVIS_RUST_VERSION" = "nightly"; then
@@ -0,0 +1,88 @@
+// Copyright (c) 2017 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+extern crate axum;
+extern crate guardhaus;
+extern crate tokio;
+
+use axum::Router;
+use axum::body::Bytes;
+use axum::http::StatusCode;
+use axum::http::header::WWW_AUTHENTICATE;
+use axum::response::{IntoResponse, Response};
+use axum::routing::any;
+use guardhaus::challenge::Challenge;
+use guardhaus::digest::{Digest, DigestRejection};
+use guardhaus::types::{HashAlgorithm, Qop};
+use std::future::{self, IntoFuture, Ready};
+
+const LISTEN: &str = "127.0.0.1:1337";
+const USERNAME: &str = "Spy";
+const PASSWORD: &str = "vs. Spy";
+const REALM: &str = "MadMag";
+// A real server should generate a unique nonce per challenge, and keep track of which ones it
+// has issued.
+const NONCE: &str = "7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v";
+
+fn unauthorized() -> Response {
+    let challenge = Challenge {
+        realm: REALM.to_owned(),
+        domain: vec![],
+        nonce: NONCE.to_owned(),
+        opaque: None,
+        stale: false,
+        algorithm: HashAlgorithm::SHA256,
+        qop: vec![Qop::Auth],
+        charset: None,
+        userhash: false,
+    };
+    (StatusCode::UNAUTHORIZED, [(WWW_AUTHENTICATE, challenge.to_string())]).into_response()
+}
+
+fn is_authorized(digest: &Digest, entity_body: &[u8]) -> bool {
+    digest.username.as_bytes() == USERNAME.as_bytes() && digest.realm == REALM &&
+        digest.nonce == NONCE &&
+        digest.response_matches_password(
+            digest.request_method_as_str().unwrap_or_default(),
+            entity_body,
+            PASSWORD,
+        )
+}
+
+fn authenticate(digest: Result<Digest, DigestRejection>, entity_body: Bytes) -> Ready<Response> {
+    let response = match digest {
+        Ok(ref digest) if is_authorized(digest, &entity_body) => "Welcome, Spy\n".into_response(),
+        _ => unauthorized(),
+    };
+    future::ready(response)
+}
+
+fn main() {
+    let app = Router::new().route("/", any(authenticate));
+    let runtime = tokio::runtime::Runtime::new().expect("Could not create async runtime");
+    let _guard = runtime.enter();
+    let listener = std::net::TcpListener::bind(LISTEN).expect("Could not bind listen address");
+    listener.set_nonblocking(true).expect("Could not set listener to non-blocking");
+    let listener = tokio::net::TcpListener::from_std(listener).expect("Could not create listener");
+    println!("Listening on {}", LISTEN);
+    runtime
+        .block_on(axum::serve(listener, app).into_future())
+        .expect("Could not run HTTP server");
+}
@@ -23,7 +23,7 @@ extern crate guardhaus;
 extern crate rpassword;
 
 use getopts::Options;
-use guardhaus::digest::{Digest, Username};
+use guardhaus::digest::Digest;
 use guardhaus::types::HashAlgorithm;
 use rpassword::prompt_password_stdout;
 use std::env;
@@ -40,14 +40,14 @@ fn open_passwdfile(path: String, create_passwdfile: bool) -> io::Result<File> {
     if create_passwdfile {
         File::create(path)
     } else {
-        OpenOptions::new().write(true).append(true).open(path)
+        OpenOptions::new().append(true).open(path)
     }
 }
 
 fn getpass(prompt: &str) -> String {
     match prompt_password_stdout(prompt) {
         Ok(password) => password,
-        Err(failure) => panic!(failure.to_string()),
+        Err(failure) => panic!("{}", failure.to_string()),
     }
 }
 
@@ -61,15 +61,11 @@ fn get_password() -> String {
     }
 }
 
+#[allow(deprecated)]
 fn append_to_passwdfile(file: &mut File, username: &str, realm: &str, password: String) {
-    let hashed = Digest::simple_hashed_a1(
-        &HashAlgorithm::MD5,
-        Username::Plain(username.to_string()),
-        realm.to_string(),
-        password,
-    );
-    if let Err(failure) = write!(file, "{}:{}:{}\n", username, realm, hashed) {
-        panic!(failure.to_string())
+    let hashed = Digest::simple_hashed_a1(&HashAlgorithm::MD5, username, realm, &password);
+    if let Err(failure) = writeln!(file, "{}:{}:{}", username, realm, hashed) {
+        panic!("{}", failure.to_string())
     }
 }
 
@@ -87,7 +83,7 @@ fn main() {
 
     let matches = match opts.parse(&args[1..]) {
         Ok(opt) => opt,
-        Err(failure) => panic!(failure.to_string()),
+        Err(failure) => panic!("{}", failure.to_string()),
     };
 
     if matches.opt_present("h") {
@@ -105,10 +101,9 @@ fn main() {
             Ok(mut passwdfile) => {
                 append_to_passwdfile(&mut passwdfile, username, realm, get_password())
             }
-            Err(failure) => panic!(failure.to_string()),
+            Err(failure) => panic!("{}", failure.to_string()),
         }
     } else {
         print_usage(&program, &opts);
-        return;
-    };
+    }
 }
@@ -25,13 +25,14 @@ extern crate hyper;
 use futures::{Future, Stream};
 use futures::future::FutureResult;
 use guardhaus::digest::{Digest, Username};
+use guardhaus::hyper_compat::MethodExt;
 use hyper::header::Authorization;
 use hyper::server::{Http, Request, Response, Service};
 use hyper::StatusCode;
 
-const LISTEN: &'static str = "127.0.0.1:1337";
-const USERNAME: &'static str = "Spy";
-const PASSWORD: &'static str = "vs. Spy";
+const LISTEN: &str = "127.0.0.1:1337";
+const USERNAME: &str = "Spy";
+const PASSWORD: &str = "vs. Spy";
 // const REALM: &'static str = "MadMag";
 
 #[derive(Clone, Copy)]
@@ -49,18 +50,19 @@ impl Service for AuthEndpoint {
         if let Some(auth) = headers.get::<Authorization<Digest>>() {
             let username = Username::Plain(USERNAME.to_owned());
             let password = PASSWORD.to_owned();
-            let method = req.method().clone();
+            let method = req.method().as_str().to_owned();
             let entity_body = req.body().concat2().wait().unwrap().to_vec().clone();
-            if auth.0.validate_using_userhash_and_password(
-                method,
+            match auth.0.validate_using_userhash_and_password(
+                &method,
                 entity_body.as_slice(),
                 username,
                 password,
-            )
-            {
-                response.set_status(StatusCode::Ok);
-            } else {
-                response.set_status(StatusCode::Forbidden);
+            ) {
+                Ok(()) => response.set_status(StatusCode::Ok),
+                Err(failure) => {
+                    eprintln!("Authentication failed: {}", failure);
+                    response.set_status(StatusCode::Forbidden);
+                }
             }
         } else {
             response.set_status(StatusCode::Unauthorized);
@@ -23,12 +23,12 @@
 use hyper::{Error as HyperError, Result as HyperResult};
 use hyper::header::{Formatter, Header, Raw};
 use hyper::header::parsing::from_one_raw_str;
-use parsing::{append_parameter, parse_parameters, unraveled_map_value};
-use std::collections::HashMap;
 use std::fmt;
 use std::str::FromStr;
+use super::digest::Digest;
+use super::error::DigestError;
+use super::parsing::{append_parameter, parse_parameters, unraveled_param_value};
 use super::types::{NonceCount, Qop};
-use unicase::UniCase;
 
 mod test;
 
@@ -48,28 +48,94 @@ pub struct AuthenticationInfo {
     pub nonce_count: Option<NonceCount>,
 }
 
-fn parse_digest(map: &HashMap<UniCase<String>, String>) -> Result<Option<String>, HyperError> {
-    if let Some(rspauth) = unraveled_map_value(map, "rspauth") {
-        if unraveled_map_value(map, "digest").is_some() {
+fn parse_digest(params: &[(&str, &str)]) -> Result<Option<String>, HyperError> {
+    if let Some(rspauth) = unraveled_param_value(params, "rspauth") {
+        if unraveled_param_value(params, "digest").is_some() {
             Err(HyperError::Header)
         } else {
             Ok(Some(rspauth))
         }
-    } else if let Some(digest) = unraveled_map_value(map, "digest") {
+    } else if let Some(digest) = unraveled_param_value(params, "digest") {
         Ok(Some(digest))
     } else {
         Ok(None)
     }
 }
 
+/// Verifies the `rspauth` of an `Authentication-Info` header, which proves that the server knows
+/// the password, given the `Digest` that the client sent and the entity body of the response.
+///
+/// As defined in [RFC 7616, section 3.5](https://tools.ietf.org/html/rfc7616#section-3.5),
+/// `rspauth` is generated like the `response` of the `Digest`, except that the method in A2 is
+/// empty, so no method is needed. The quality of protection, client nonce, and nonce count must
+/// match the `Digest` if they are present.
+pub fn verify_rspauth(
+    info: &AuthenticationInfo,
+    original_digest: &Digest,
+    entity_body: &[u8],
+    password: &str,
+) -> bool {
+    check_rspauth(info, original_digest, |digest| {
+        digest.using_password("", entity_body, password.to_owned())
+    })
+}
+
+/// Verifies the `rspauth` of an `Authentication-Info` header like `verify_rspauth`, given a
+/// hexadecimal digest of an A1 string instead of the password.
+pub fn verify_rspauth_using_hashed_a1(
+    info: &AuthenticationInfo,
+    original_digest: &Digest,
+    entity_body: &[u8],
+    hashed_a1: &str,
+) -> bool {
+    check_rspauth(info, original_digest, |digest| {
+        generate_rspauth(digest, entity_body, hashed_a1)
+    })
+}
+
+fn check_rspauth<F>(info: &AuthenticationInfo, original_digest: &Digest, generate: F) -> bool
+where
+    F: FnOnce(&Digest) -> Result<String, DigestError>,
+{
+    let rspauth = match info.digest {
+        Some(ref rspauth) => rspauth,
+        None => return false,
+    };
+    if info.qop.is_some() && info.qop != original_digest.qop ||
+        info.client_nonce.is_some() && info.client_nonce != original_digest.client_nonce ||
+        info.nonce_count.is_some() && info.nonce_count != original_digest.nonce_count
+    {
+        return false;
+    }
+    match generate(original_digest) {
+        Ok(expected) => *rspauth == expected,
+        Err(_) => false,
+    }
+}
+
+/// Generates the `rspauth` of an `Authentication-Info` header for the response to a request
+/// authorized by `digest`, given the entity body of the response and a hexadecimal digest of an
+/// A1 string, as defined in
+/// [RFC 2617, section 3.2.3](https://tools.ietf.org/html/rfc2617#section-3.2.3).
+///
+/// `rspauth` is generated like the `response` of the `Digest`, except that the method in A2 is
+/// empty, so A2 is only `:` followed by the URI (and the entity body digest for `auth-int`).
+pub fn generate_rspauth(
+    digest: &Digest,
+    entity_body: &[u8],
+    hashed_a1: &str,
+) -> Result<String, DigestError> {
+    digest.using_hashed_a1("", entity_body, hashed_a1.to_owned())
+}
+
 impl FromStr for AuthenticationInfo {
     type Err = HyperError;
 
     fn from_str(s: &str) -> Result<AuthenticationInfo, HyperError> {
-        let parameters = parse_parameters(s);
+        let parameters = parse_parameters(s)?;
         let digest = parse_digest(&parameters)?;
         let qop = Qop::from_parameters(&parameters)?;
-        let client_nonce = unraveled_map_value(&parameters, "cnonce");
+        let client_nonce = unraveled_param_value(&parameters, "cnonce");
         let nonce_count = NonceCount::from_parameters(&parameters)?;
 
         if qop.is_some() && (digest.is_none() || client_nonce.is_none() || nonce_count.is_none()) {
@@ -77,11 +143,11 @@ impl FromStr for AuthenticationInfo {
         }
 
         Ok(AuthenticationInfo {
-            digest: digest,
-            next_nonce: unraveled_map_value(&parameters, "nextnonce"),
-            qop: qop,
-            client_nonce: client_nonce,
-            nonce_count: nonce_count,
+            digest,
+            next_nonce: unraveled_param_value(&parameters, "nextnonce"),
+            qop,
+            client_nonce,
+            nonce_count,
         })
     }
 }
@@ -20,10 +20,13 @@
 
 #![cfg(test)]
 
-use parsing::test_helper::{assert_header_parsing_error, assert_parsed_header_equal,
-                           assert_serialized_header_equal};
-use super::AuthenticationInfo;
-use super::super::types::{NonceCount, Qop};
+use super::{AuthenticationInfo, generate_rspauth, verify_rspauth,
+            verify_rspauth_using_hashed_a1};
+use super::super::digest::test_helper::rfc2617_digest_header;
+use super::super::parsing::test_helper::{assert_header_parsing_error,
+                                         assert_parsed_header_equal,
+                                         assert_serialized_header_equal};
+use super::super::types::{HashAlgorithm, NonceCount, Qop};
 
 #[test]
 fn test_parse_authentication_info_with_digest_and_nextnonce() {
@@ -80,6 +83,12 @@ fn test_parse_authentication_info_with_bad_qop() {
     assert_header_parsing_error::<AuthenticationInfo>("qop=invalid");
 }
 
+#[test]
+fn test_parse_authentication_info_with_half_quoted_value() {
+    assert_header_parsing_error::<AuthenticationInfo>("nextnonce=\"fedcba");
+    assert_header_parsing_error::<AuthenticationInfo>("nextnonce=fedcba\"");
+}
+
 #[test]
 fn test_parse_authentication_info_with_nextnonce() {
     let expected = AuthenticationInfo {
@@ -187,3 +196,96 @@ fn test_fmt_authentication_info_with_nonce_count() {
     };
     assert_serialized_header_equal(header, "Authentication-Info: nc=000000ff");
 }
+
+const PASSWORD: &str = "Circle Of Life";
+
+fn rspauth_info(rspauth: &str, qop: Qop) -> AuthenticationInfo {
+    AuthenticationInfo {
+        digest: Some(rspauth.to_owned()),
+        next_nonce: None,
+        qop: Some(qop),
+        client_nonce: Some("0a4f113b".to_owned()),
+        nonce_count: Some(NonceCount(1)),
+    }
+}
+
+#[test]
+fn test_verify_rspauth() {
+    let digest = rfc2617_digest_header(HashAlgorithm::SHA256);
+    let info = rspauth_info(
+        "4e45f148392186049914ceaa233084f1670479136368ed2616253aef371956df",
+        Qop::Auth,
+    );
+    assert!(verify_rspauth(&info, &digest, b"", PASSWORD));
+    assert!(!verify_rspauth(&info, &digest, b"", "Circle of Life"));
+
+    let parsed: AuthenticationInfo = info.to_string().parse().unwrap();
+    assert!(verify_rspauth(&parsed, &digest, b"", PASSWORD));
+}
+
+#[test]
+fn test_verify_rspauth_with_auth_int() {
+    let mut digest = rfc2617_digest_header(HashAlgorithm::SHA256);
+    digest.qop = Some(Qop::AuthInt);
+    let info = rspauth_info(
+        "828c8511a0ccc68b35444683db01025d6caffd971ce9ab53f6bcd70a3c02d5e7",
+        Qop::AuthInt,
+    );
+    assert!(verify_rspauth(&info, &digest, b"<html></html>", PASSWORD));
+    assert!(!verify_rspauth(&info, &digest, b"<html>", PASSWORD));
+}
+
+#[test]
+fn test_verify_rspauth_with_mismatched_parameters() {
+    let digest = rfc2617_digest_header(HashAlgorithm::SHA256);
+    let rspauth = "4e45f148392186049914ceaa233084f1670479136368ed2616253aef371956df";
+
+    let mut info = rspauth_info(rspauth, Qop::AuthInt);
+    assert!(!verify_rspauth(&info, &digest, b"", PASSWORD));
+    info = rspauth_info(rspauth, Qop::Auth);
+    info.client_nonce = Some("deadbeef".to_owned());
+    assert!(!verify_rspauth(&info, &digest, b"", PASSWORD));
+    info = rspauth_info(rspauth, Qop::Auth);
+    info.nonce_count = Some(NonceCount(2));
+    assert!(!verify_rspauth(&info, &digest, b"", PASSWORD));
+    info = rspauth_info(rspauth, Qop::Auth);
+    info.digest = None;
+    assert!(!verify_rspauth(&info, &digest, b"", PASSWORD));
+}
+
+// See: RFC 2617, Section 3.5. The RFC does not list the rspauth of its example, so the expected
+// value was computed independently.
+const RFC2617_HASHED_A1: &str = "939e7578ed9e3c518a452acee763bce9";
+const RFC2617_RSPAUTH: &str = "376602cfd2f4e8e5e78b948a85263e85";
+
+#[allow(deprecated)]
+#[test]
+fn test_generate_rspauth() {
+    let digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    assert_eq!(
+        Ok(RFC2617_RSPAUTH.to_owned()),
+        generate_rspauth(&digest, b"", RFC2617_HASHED_A1)
+    );
+    assert_eq!(
+        Ok(RFC2617_RSPAUTH.to_owned()),
+        digest.using_password("", b"", PASSWORD.to_owned())
+    );
+}
+
+#[allow(deprecated)]
+#[test]
+fn test_verify_rspauth_using_hashed_a1() {
+    let digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    let info = rspauth_info(RFC2617_RSPAUTH, Qop::Auth);
+    assert!(verify_rspauth_using_hashed_a1(&info, &digest, b"", RFC2617_HASHED_A1));
+    assert!(verify_rspauth(&info, &digest, b"", PASSWORD));
+    assert!(!verify_rspauth_using_hashed_a1(
+        &info,
+        &digest,
+        b"",
+        "00000000000000000000000000000000",
+    ));
+
+    let mismatched = rspauth_info(RFC2617_RSPAUTH, Qop::AuthInt);
+    assert!(!verify_rspauth_using_hashed_a1(&mismatched, &digest, b"", RFC2617_HASHED_A1));
+}
@@ -0,0 +1,407 @@
+// Copyright (c) 2017 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! An implementation of the `WWW-Authenticate` header, when using the `Digest` scheme.
+
+#[cfg(feature = "http")]
+use http::HeaderValue;
+use hyper::{Error as HyperError, Result as HyperResult};
+use hyper::header::{Charset, Formatter, Header, Raw};
+use hyper::header::parsing::from_one_raw_str;
+use std::fmt;
+use std::str::FromStr;
+use super::digest::{Digest, check_no_crlf, uri_in_domain};
+use super::error::DigestError;
+use super::parsing::{append_parameter, parse_parameters, strip_scheme, unraveled_param_value};
+use super::types::{HashAlgorithm, Qop};
+use unicase::UniCase;
+
+mod test;
+#[cfg(test)]
+pub mod test_helper;
+
+/// Parameters for the `WWW-Authenticate` header when using the `Digest` scheme.
+///
+/// The parameters are described in more detail in
+/// [RFC 7616](https://tools.ietf.org/html/rfc7616#section-3.3).
+/// Unless otherwise noted, the parameter name maps to the struct variable name.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Challenge {
+    /// Authentication realm.
+    pub realm: String,
+    /// The URIs that define the protection space. An empty list means that the protection space
+    /// is not restricted.
+    pub domain: Vec<String>,
+    /// Cryptographic nonce.
+    pub nonce: String,
+    /// Optional opaque string, which the client returns unchanged.
+    pub opaque: Option<String>,
+    /// Whether the previous request was rejected because its nonce was stale.
+    pub stale: bool,
+    /// The hash algorithm the client should use when generating the `response`.
+    pub algorithm: HashAlgorithm,
+    /// The qualities of protection supported by the server. Empty only in RFC 2069 mode.
+    pub qop: Vec<Qop>,
+    /// The character set the server expects the username and password to use. Added for
+    /// RFC 7616.
+    pub charset: Option<Charset>,
+    /// Whether the server supports userhashes. Added for RFC 7616.
+    pub userhash: bool,
+}
+
+fn parse_flag(value: Option<String>) -> Result<bool, HyperError> {
+    if let Some(value) = value {
+        let flag = UniCase::new(value);
+        if flag == UniCase::new("true".to_owned()) {
+            Ok(true)
+        } else if flag == UniCase::new("false".to_owned()) {
+            Ok(false)
+        } else {
+            Err(HyperError::Header)
+        }
+    } else {
+        Ok(false)
+    }
+}
+
+impl Challenge {
+    /// Whether the previous request was rejected only because its nonce was stale, in which case
+    /// the client can retry with the new nonce without asking the user for credentials again.
+    pub fn is_stale(&self) -> bool {
+        self.stale
+    }
+
+    /// Whether `uri` is in the protection space of this challenge, i.e. the `domain` parameter is
+    /// empty (the protection space is not restricted), or one of its URIs is a prefix of `uri`, as
+    /// defined in [RFC 7616, section 3.3](https://tools.ietf.org/html/rfc7616#section-3.3).
+    ///
+    /// Dot segments are resolved and percent-encoding is normalized first, and prefixes only
+    /// match whole path segments, so a domain of `/api` does not cover `/apiary`.
+    pub fn covers_uri(&self, uri: &str) -> bool {
+        self.domain.is_empty() || self.domain.iter().any(|domain| uri_in_domain(uri, domain))
+    }
+
+    /// Checks that the fields which may originate from external input (the realm, domain, nonce,
+    /// and opaque string) contain no carriage returns or line feeds.
+    ///
+    /// `ChallengeBuilder::build` and `to_www_authenticate_header_value` run this check, but
+    /// `Display` does not, so servers which build challenges from untrusted input in other ways
+    /// should check them before sending them.
+    pub fn validate_no_crlf(&self) -> Result<(), DigestError> {
+        check_no_crlf("realm", &self.realm)?;
+        for domain in &self.domain {
+            check_no_crlf("domain", domain)?;
+        }
+        check_no_crlf("nonce", &self.nonce)?;
+        if let Some(ref opaque) = self.opaque {
+            check_no_crlf("opaque", opaque)?;
+        }
+        Ok(())
+    }
+
+    /// Serializes this challenge as the value of an `http` crate `WWW-Authenticate` header,
+    /// including the `Digest` scheme.
+    ///
+    /// Returns the error of `validate_no_crlf` if it fails, and `DigestError::InvalidHeader` if a
+    /// parameter contains other control characters, which are not allowed in header values.
+    #[cfg(feature = "http")]
+    pub fn to_www_authenticate_header_value(&self) -> Result<HeaderValue, DigestError> {
+        self.validate_no_crlf()?;
+        HeaderValue::from_str(&self.to_string()).map_err(|_| DigestError::InvalidHeader)
+    }
+}
+
+/// Generates a challenge for a `Digest` whose nonce has expired, as described in
+/// [RFC 7616, section 3.3](https://tools.ietf.org/html/rfc7616#section-3.3).
+///
+/// The realm, opaque string, qop, charset, and userhash support are copied from the expired
+/// `Digest`, and `stale` is set, so that the client retries with `new_nonce` without prompting
+/// the user again.
+pub fn generate_stale_challenge(
+    expired_digest: &Digest,
+    new_nonce: String,
+    algorithm: HashAlgorithm,
+) -> Challenge {
+    Challenge {
+        realm: expired_digest.realm.clone(),
+        domain: vec![],
+        nonce: new_nonce,
+        opaque: expired_digest.opaque.clone(),
+        stale: true,
+        algorithm,
+        qop: expired_digest.qop.iter().cloned().collect(),
+        charset: expired_digest.charset.clone(),
+        userhash: expired_digest.userhash,
+    }
+}
+
+// Whether the rest of a header value, following a comma, starts a new challenge: an auth-scheme
+// is followed by whitespace (or ends the value), whereas an auth-param name is followed by `=`.
+fn starts_challenge(rest: &str) -> bool {
+    let rest = rest.trim_start();
+    match rest.find([' ', '=', ',']) {
+        Some(end) => rest[end..].starts_with(' ') && !rest[end..].trim_start().starts_with('='),
+        None => !rest.is_empty(),
+    }
+}
+
+// Splits a `WWW-Authenticate` header value into its challenges, ignoring commas inside of quoted
+// strings.
+fn split_challenges(value: &str) -> Vec<&str> {
+    let mut challenges = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (index, character) in value.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match character {
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ',' if !quoted && starts_challenge(&value[index + 1..]) => {
+                challenges.push(&value[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    challenges.push(&value[start..]);
+
+    challenges
+        .into_iter()
+        .map(str::trim)
+        .filter(|challenge| !challenge.is_empty())
+        .collect()
+}
+
+/// Parses the `Digest` challenges in a `WWW-Authenticate` header value, which may contain several
+/// challenges, e.g. one per algorithm. Challenges using other schemes, and `Digest` challenges
+/// which cannot be parsed, are skipped.
+pub fn parse_challenges(value: &str) -> Vec<Challenge> {
+    split_challenges(value)
+        .into_iter()
+        .filter_map(|challenge| Challenge::from_str(challenge).ok())
+        .collect()
+}
+
+/// Parses the `Digest` challenges in a `WWW-Authenticate` header value, which may contain several
+/// challenges separated by commas, as in
+/// [RFC 7616, section 3.9.1](https://tools.ietf.org/html/rfc7616#section-3.9.1).
+///
+/// Unlike `parse_challenges`, this fails if a `Digest` challenge cannot be parsed, or if the value
+/// contains no `Digest` challenge at all. Challenges using other schemes are skipped.
+pub fn parse_www_authenticate(header_value: &str) -> Result<Vec<Challenge>, DigestError> {
+    let challenges = split_challenges(header_value)
+        .into_iter()
+        .filter(|challenge| strip_scheme(challenge, "Digest").is_some())
+        .map(|challenge| {
+            Challenge::from_str(challenge).map_err(|_| DigestError::InvalidHeader)
+        })
+        .collect::<Result<Vec<Challenge>, DigestError>>()?;
+    if challenges.is_empty() {
+        return Err(DigestError::MissingSchemePrefix);
+    }
+    Ok(challenges)
+}
+
+/// Builds a `Challenge`, e.g. for the `WWW-Authenticate` header of a `401 Unauthorized` response.
+///
+/// Unless set otherwise, the challenge uses SHA-256, offers no quality of protection, and has no
+/// domain, opaque string, or charset.
+#[derive(Clone, Debug)]
+pub struct ChallengeBuilder {
+    challenge: Challenge,
+}
+
+impl ChallengeBuilder {
+    /// Creates a builder for a challenge with the given realm and nonce.
+    pub fn new(realm: impl Into<String>, nonce: impl Into<String>) -> ChallengeBuilder {
+        ChallengeBuilder {
+            challenge: Challenge {
+                realm: realm.into(),
+                domain: vec![],
+                nonce: nonce.into(),
+                opaque: None,
+                stale: false,
+                algorithm: HashAlgorithm::SHA256,
+                qop: vec![],
+                charset: None,
+                userhash: false,
+            },
+        }
+    }
+
+    /// Sets the algorithm used to produce the digest.
+    pub fn algorithm(mut self, algorithm: HashAlgorithm) -> ChallengeBuilder {
+        self.challenge.algorithm = algorithm;
+        self
+    }
+
+    /// Sets the supported quality of protection options.
+    pub fn qop_options(mut self, qop: Vec<Qop>) -> ChallengeBuilder {
+        self.challenge.qop = qop;
+        self
+    }
+
+    /// Sets the opaque string, which the client returns unchanged.
+    pub fn opaque(mut self, opaque: impl Into<String>) -> ChallengeBuilder {
+        self.challenge.opaque = Some(opaque.into());
+        self
+    }
+
+    /// Sets the URIs which define the protection space.
+    pub fn domain(mut self, domain: Vec<String>) -> ChallengeBuilder {
+        self.challenge.domain = domain;
+        self
+    }
+
+    /// Sets whether the previous request was rejected only because its nonce was stale.
+    pub fn stale(mut self, stale: bool) -> ChallengeBuilder {
+        self.challenge.stale = stale;
+        self
+    }
+
+    /// Sets the charset which the server expects the username and password to be encoded in.
+    pub fn charset(mut self, charset: Charset) -> ChallengeBuilder {
+        self.challenge.charset = Some(charset);
+        self
+    }
+
+    /// Sets whether the server supports userhashes.
+    pub fn userhash(mut self, userhash: bool) -> ChallengeBuilder {
+        self.challenge.userhash = userhash;
+        self
+    }
+
+    /// Returns the built `Challenge`, or an error if the nonce is empty or a field contains a
+    /// carriage return or line feed (see `Challenge::validate_no_crlf`).
+    pub fn build(self) -> Result<Challenge, DigestError> {
+        if self.challenge.nonce.is_empty() {
+            return Err(DigestError::EmptyNonce);
+        }
+        self.challenge.validate_no_crlf()?;
+        Ok(self.challenge)
+    }
+}
+
+impl FromStr for Challenge {
+    type Err = HyperError;
+
+    fn from_str(s: &str) -> Result<Challenge, HyperError> {
+        let parameters = match strip_scheme(s, "Digest") {
+            Some(rest) => parse_parameters(rest)?,
+            None => return Err(HyperError::Header),
+        };
+        let realm = match unraveled_param_value(&parameters, "realm") {
+            Some(value) => value,
+            None => return Err(HyperError::Header),
+        };
+        let nonce = match unraveled_param_value(&parameters, "nonce") {
+            Some(value) => value,
+            None => return Err(HyperError::Header),
+        };
+        let domain = match unraveled_param_value(&parameters, "domain") {
+            Some(value) => value.split_whitespace().map(|uri| uri.to_owned()).collect(),
+            None => vec![],
+        };
+        let algorithm = unraveled_param_value(&parameters, "algorithm")
+            .map(|value| HashAlgorithm::from_str(&value))
+            .transpose()?
+            .unwrap_or_default();
+        let qop = match unraveled_param_value(&parameters, "qop") {
+            Some(value) => Qop::parse_list(&value)?,
+            None => vec![],
+        };
+        let charset = match unraveled_param_value(&parameters, "charset") {
+            Some(ref value) if UniCase::new(value.clone()) == UniCase::new("utf-8".to_owned()) => {
+                Some(Charset::Ext("UTF-8".to_owned()))
+            }
+            Some(_) => return Err(HyperError::Header),
+            None => None,
+        };
+
+        Ok(Challenge {
+            realm,
+            domain,
+            nonce,
+            opaque: unraveled_param_value(&parameters, "opaque"),
+            stale: parse_flag(unraveled_param_value(&parameters, "stale"))?,
+            algorithm,
+            qop,
+            charset,
+            userhash: parse_flag(unraveled_param_value(&parameters, "userhash"))?,
+        })
+    }
+}
+
+impl Header for Challenge {
+    fn header_name() -> &'static str {
+        "WWW-Authenticate"
+    }
+
+    fn parse_header(raw: &Raw) -> HyperResult<Challenge> {
+        from_one_raw_str(raw).and_then(|s: String| Challenge::from_str(&s[..]))
+    }
+
+    fn fmt_header(&self, f: &mut Formatter) -> fmt::Result {
+        f.fmt_line(self)
+    }
+}
+
+/// Serializes the challenge as a `WWW-Authenticate` header value.
+///
+/// Fields are not checked for header injection here. `ChallengeBuilder::build` and
+/// `validator::generate_401_response` check them, so challenges built in other ways should be
+/// checked via `validate_no_crlf` before they are sent.
+impl fmt::Display for Challenge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut serialized = String::new();
+        append_parameter(&mut serialized, "realm", &self.realm, true);
+        if !self.domain.is_empty() {
+            append_parameter(&mut serialized, "domain", &self.domain.join(" "), true);
+        }
+        append_parameter(&mut serialized, "nonce", &self.nonce, true);
+        if let Some(ref opaque) = self.opaque {
+            append_parameter(&mut serialized, "opaque", opaque, true);
+        }
+        if self.stale {
+            append_parameter(&mut serialized, "stale", "true", false);
+        }
+        append_parameter(
+            &mut serialized,
+            "algorithm",
+            &self.algorithm.to_string(),
+            false,
+        );
+        if !self.qop.is_empty() {
+            let qop: Vec<String> = self.qop.iter().map(|qop| qop.to_string()).collect();
+            append_parameter(&mut serialized, "qop", &qop.join(", "), true);
+        }
+        if let Some(ref charset) = self.charset {
+            append_parameter(&mut serialized, "charset", &charset.to_string(), false);
+        }
+        if self.userhash {
+            append_parameter(&mut serialized, "userhash", "true", false);
+        }
+        write!(f, "Digest {}", serialized)
+    }
+}
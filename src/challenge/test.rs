@@ -0,0 +1,386 @@
+// Copyright (c) 2017 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+#![cfg(test)]
+
+use hyper::header::Charset;
+use super::{Challenge, ChallengeBuilder, parse_challenges, parse_www_authenticate};
+use super::test_helper::rfc7616_challenge;
+use super::super::error::DigestError;
+use super::super::parsing::test_helper::{assert_header_parsing_error,
+                                         assert_parsed_header_equal,
+                                         assert_serialized_header_equal};
+use super::super::types::{HashAlgorithm, Qop};
+
+#[test]
+fn test_parse_challenge() {
+    assert_parsed_header_equal(
+        rfc7616_challenge(HashAlgorithm::SHA256),
+        "Digest realm=\"http-auth@example.org\", qop=\"auth, auth-int\", algorithm=SHA-256, \
+         nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", \
+         opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\"",
+    )
+}
+
+#[test]
+fn test_parse_challenge_with_percent_encoded_realm() {
+    let parse_realm = |s: &str| s.parse::<Challenge>().map(|challenge| challenge.realm).ok();
+    assert_eq!(
+        Some("@".to_owned()),
+        parse_realm("Digest realm=%40, nonce=\"7ypf/xlj9XXwfDPEoM4URrv\"")
+    );
+    assert_eq!(
+        Some("%40".to_owned()),
+        parse_realm("Digest realm=\"%40\", nonce=\"7ypf/xlj9XXwfDPEoM4URrv\"")
+    );
+}
+
+#[allow(deprecated)]
+#[test]
+fn test_parse_challenge_with_lowercase_scheme() {
+    assert_parsed_header_equal(
+        rfc7616_challenge(HashAlgorithm::MD5),
+        "digest realm=\"http-auth@example.org\", qop=\"auth, auth-int\", \
+         nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", \
+         opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\"",
+    )
+}
+
+#[test]
+fn test_parse_challenge_with_all_parameters() {
+    let expected = Challenge {
+        realm: "api@example.org".to_owned(),
+        domain: vec!["/api".to_owned(), "https://example.org/doe.json".to_owned()],
+        nonce: "5TsQWLVdgBdmrQ0XsxbDODV+57QdFR34I9HAbC/RVvkK".to_owned(),
+        opaque: Some("HRPCssKJSGjCrkzDg8OhwpzCiGPChXYjwrI2QmXDnsOS".to_owned()),
+        stale: true,
+        algorithm: HashAlgorithm::SHA512256,
+        qop: vec![Qop::Auth],
+        charset: Some(Charset::Ext("UTF-8".to_owned())),
+        userhash: true,
+    };
+    assert_parsed_header_equal(
+        expected,
+        "Digest realm=\"api@example.org\", domain=\"/api https://example.org/doe.json\", \
+         qop=\"auth\", algorithm=SHA-512-256, \
+         nonce=\"5TsQWLVdgBdmrQ0XsxbDODV+57QdFR34I9HAbC/RVvkK\", \
+         opaque=\"HRPCssKJSGjCrkzDg8OhwpzCiGPChXYjwrI2QmXDnsOS\", stale=TRUE, charset=UTF-8, \
+         userhash=true",
+    )
+}
+
+#[test]
+fn test_covers_uri_with_empty_domain() {
+    let challenge = rfc7616_challenge(HashAlgorithm::SHA256);
+    assert!(challenge.covers_uri("/"));
+    assert!(challenge.covers_uri("/admin/users"));
+}
+
+#[test]
+fn test_covers_uri_with_domain() {
+    let mut challenge = rfc7616_challenge(HashAlgorithm::SHA256);
+    challenge.domain = vec!["/api/".to_owned(), "https://example.org/docs/".to_owned()];
+    assert!(challenge.covers_uri("/api/"));
+    assert!(challenge.covers_uri("/api/v1/users"));
+    assert!(challenge.covers_uri("https://example.org/docs/index.html"));
+    assert!(challenge.covers_uri("/docs/index.html"));
+    assert!(!challenge.covers_uri("/admin/users"));
+    assert!(!challenge.covers_uri("/api"));
+    assert!(!challenge.covers_uri("https://example.com/docs/index.html"));
+}
+
+#[test]
+fn test_covers_uri_at_segment_boundaries() {
+    let mut challenge = rfc7616_challenge(HashAlgorithm::SHA256);
+    challenge.domain = vec!["/api".to_owned()];
+    assert!(challenge.covers_uri("/api"));
+    assert!(challenge.covers_uri("/api/v1/users"));
+    assert!(challenge.covers_uri("/api?page=2"));
+    assert!(challenge.covers_uri("/%61pi/v1/users"));
+    assert!(!challenge.covers_uri("/apiary"));
+    assert!(!challenge.covers_uri("/api/../admin"));
+    assert!(!challenge.covers_uri("/api/%2E%2E/admin"));
+}
+
+#[test]
+fn test_parse_challenge_with_wrong_scheme() {
+    assert_header_parsing_error::<Challenge>(
+        "Basic realm=\"http-auth@example.org\", \
+         nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\"",
+    )
+}
+
+#[test]
+fn test_parse_challenge_with_no_realm() {
+    assert_header_parsing_error::<Challenge>(
+        "Digest nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\"",
+    )
+}
+
+#[test]
+fn test_parse_challenge_with_no_nonce() {
+    assert_header_parsing_error::<Challenge>("Digest realm=\"http-auth@example.org\"")
+}
+
+#[test]
+fn test_parse_challenge_with_bad_qop() {
+    assert_header_parsing_error::<Challenge>(
+        "Digest realm=\"http-auth@example.org\", qop=\"auth, invalid\", \
+         nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\"",
+    )
+}
+
+#[test]
+fn test_parse_challenge_with_bad_stale_flag() {
+    assert_header_parsing_error::<Challenge>(
+        "Digest realm=\"http-auth@example.org\", stale=maybe, \
+         nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\"",
+    )
+}
+
+#[test]
+fn test_fmt_challenge() {
+    assert_serialized_header_equal(
+        rfc7616_challenge(HashAlgorithm::SHA256),
+        "WWW-Authenticate: Digest realm=\"http-auth@example.org\", \
+         nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", \
+         opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\", algorithm=SHA-256, \
+         qop=\"auth, auth-int\"",
+    )
+}
+
+#[test]
+fn test_display_challenge() {
+    let challenge = rfc7616_challenge(HashAlgorithm::SHA256);
+    let displayed = format!("{}", challenge);
+    assert!(displayed.starts_with("Digest realm=\"http-auth@example.org\", "));
+    assert_eq!(Ok(challenge), displayed.parse::<Challenge>().map_err(|_| ()));
+}
+
+#[test]
+fn test_fmt_challenge_with_crlf() {
+    for line_break in &["\r", "\n", "\r\n"] {
+        let injected = format!("foo{}X-Injected: bar", line_break);
+        let mut challenges = vec![];
+        let mut challenge = rfc7616_challenge(HashAlgorithm::SHA256);
+        challenge.realm = injected.clone();
+        challenges.push(("realm", challenge));
+        let mut challenge = rfc7616_challenge(HashAlgorithm::SHA256);
+        challenge.domain = vec!["/dir".to_owned(), injected.clone()];
+        challenges.push(("domain", challenge));
+        let mut challenge = rfc7616_challenge(HashAlgorithm::SHA256);
+        challenge.nonce = injected.clone();
+        challenges.push(("nonce", challenge));
+        let mut challenge = rfc7616_challenge(HashAlgorithm::SHA256);
+        challenge.opaque = Some(injected.clone());
+        challenges.push(("opaque", challenge));
+
+        for (field, challenge) in challenges {
+            assert_eq!(Err(DigestError::CrlfInjection(field)), challenge.validate_no_crlf());
+            // Serializing never fails, so that `to_string` does not panic.
+            assert!(challenge.to_string().contains(&injected));
+        }
+    }
+    assert_eq!(Ok(()), rfc7616_challenge(HashAlgorithm::SHA256).validate_no_crlf());
+}
+
+#[allow(deprecated)]
+#[test]
+fn test_fmt_challenge_with_domain_and_flags() {
+    let mut challenge = rfc7616_challenge(HashAlgorithm::MD5);
+    challenge.domain = vec!["/dir".to_owned(), "/other".to_owned()];
+    challenge.opaque = None;
+    challenge.qop = vec![];
+    challenge.stale = true;
+    challenge.userhash = true;
+    assert_serialized_header_equal(
+        challenge,
+        "WWW-Authenticate: Digest realm=\"http-auth@example.org\", domain=\"/dir /other\", \
+         nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", stale=true, algorithm=MD5, \
+         userhash=true",
+    )
+}
+
+#[test]
+fn test_build_minimal_challenge() {
+    let challenge = ChallengeBuilder::new("http-auth@example.org", "7ypf/xlj9XXwfDPEoM4URrv")
+        .build()
+        .unwrap();
+    assert_serialized_header_equal(
+        challenge,
+        "WWW-Authenticate: Digest realm=\"http-auth@example.org\", \
+         nonce=\"7ypf/xlj9XXwfDPEoM4URrv\", algorithm=SHA-256",
+    )
+}
+
+#[test]
+fn test_build_maximal_challenge() {
+    let realm = "http-auth@example.org".to_owned();
+    let challenge = ChallengeBuilder::new(realm, "7ypf/xlj9XXwfDPEoM4URrv")
+        .algorithm(HashAlgorithm::SHA512256)
+        .qop_options(vec![Qop::Auth, Qop::AuthInt])
+        .opaque("FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS")
+        .domain(vec!["/dir".to_owned(), "https://example.org/other".to_owned()])
+        .stale(true)
+        .charset(Charset::Ext("UTF-8".to_owned()))
+        .userhash(true)
+        .build()
+        .unwrap();
+    assert_serialized_header_equal(
+        challenge,
+        "WWW-Authenticate: Digest realm=\"http-auth@example.org\", \
+         domain=\"/dir https://example.org/other\", nonce=\"7ypf/xlj9XXwfDPEoM4URrv\", \
+         opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\", stale=true, \
+         algorithm=SHA-512-256, qop=\"auth, auth-int\", charset=UTF-8, userhash=true",
+    )
+}
+
+#[test]
+fn test_build_challenge_with_empty_nonce() {
+    assert_eq!(
+        Err(DigestError::EmptyNonce),
+        ChallengeBuilder::new("http-auth@example.org", "").build()
+    );
+}
+
+#[test]
+fn test_build_challenge_with_crlf_in_realm() {
+    for realm in &["foo\rX-Injected: bar", "foo\nX-Injected: bar", "foo\r\nX-Injected: bar"] {
+        assert_eq!(
+            Err(DigestError::CrlfInjection("realm")),
+            ChallengeBuilder::new(*realm, "7ypf/xlj9XXwfDPEoM4URrv").build()
+        );
+    }
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_to_www_authenticate_header_value() {
+    use http::HeaderMap;
+    use http::header::WWW_AUTHENTICATE;
+
+    let challenge = rfc7616_challenge(HashAlgorithm::SHA256);
+    let mut headers = HeaderMap::new();
+    headers.insert(WWW_AUTHENTICATE, challenge.to_www_authenticate_header_value().unwrap());
+    assert_eq!(
+        Some(
+            "Digest realm=\"http-auth@example.org\", \
+             nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", \
+             opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\", algorithm=SHA-256, \
+             qop=\"auth, auth-int\"",
+        ),
+        headers.get("WWW-Authenticate").and_then(|value| value.to_str().ok())
+    );
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_to_www_authenticate_header_value_with_control_characters() {
+    let mut challenge = rfc7616_challenge(HashAlgorithm::SHA256);
+    challenge.realm = "a\r\nX-Injected: b".to_owned();
+    assert_eq!(
+        Err(DigestError::CrlfInjection("realm")),
+        challenge.to_www_authenticate_header_value()
+    );
+    challenge.realm = "a\u{1}b".to_owned();
+    assert_eq!(Err(DigestError::InvalidHeader), challenge.to_www_authenticate_header_value());
+}
+
+#[allow(deprecated)]
+#[test]
+fn test_parse_challenges() {
+    // See: RFC 7616, Section 3.9.1, with both challenges in one header value
+    let value = "Digest realm=\"http-auth@example.org\", qop=\"auth, auth-int\", \
+                 algorithm=SHA-256, nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", \
+                 opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\", \
+                 Digest realm=\"http-auth@example.org\", qop=\"auth, auth-int\", algorithm=MD5, \
+                 nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", \
+                 opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\"";
+    assert_eq!(
+        vec![
+            rfc7616_challenge(HashAlgorithm::SHA256),
+            rfc7616_challenge(HashAlgorithm::MD5),
+        ],
+        parse_challenges(value)
+    );
+}
+
+#[test]
+fn test_parse_challenges_with_other_schemes() {
+    let value = "Basic realm=\"Digest realm=, nonce=\", Negotiate, Bearer abc==, \
+                 Digest realm=\"http-auth@example.org\", nonce=\"abc\", algorithm=SHA-256, \
+                 Digest realm=\"invalid\", Newauth realm = \"apps\", type=1";
+    let challenges = parse_challenges(value);
+    assert_eq!(1, challenges.len());
+    assert_eq!("http-auth@example.org", challenges[0].realm);
+    assert_eq!("abc", challenges[0].nonce);
+
+    assert!(parse_challenges("").is_empty());
+    assert!(parse_challenges("Basic realm=\"simple\"").is_empty());
+}
+
+#[allow(deprecated)]
+#[test]
+fn test_parse_www_authenticate() {
+    // See: RFC 7616, Section 3.9.1, with both challenges in one header value
+    let value = "Digest realm=\"http-auth@example.org\", qop=\"auth, auth-int\", \
+                 algorithm=SHA-256, nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", \
+                 opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\", \
+                 Digest realm=\"http-auth@example.org\", qop=\"auth, auth-int\", algorithm=MD5, \
+                 nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", \
+                 opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\"";
+    assert_eq!(
+        Ok(vec![
+            rfc7616_challenge(HashAlgorithm::SHA256),
+            rfc7616_challenge(HashAlgorithm::MD5),
+        ]),
+        parse_www_authenticate(value)
+    );
+}
+
+#[test]
+fn test_parse_www_authenticate_with_distinct_nonces() {
+    let value = "Basic realm=\"simple\", \
+                 Digest realm=\"a\", qop=\"auth, auth-int\", nonce=\"nonce,a\", \
+                 Digest realm=\"b\", domain=\"/api /admin\", nonce=\"nonce,b\"";
+    let challenges = parse_www_authenticate(value).unwrap();
+    assert_eq!(2, challenges.len());
+    assert_eq!("a", challenges[0].realm);
+    assert_eq!("nonce,a", challenges[0].nonce);
+    assert_eq!(vec![Qop::Auth, Qop::AuthInt], challenges[0].qop);
+    assert_eq!("b", challenges[1].realm);
+    assert_eq!("nonce,b", challenges[1].nonce);
+    assert_eq!(vec!["/api".to_owned(), "/admin".to_owned()], challenges[1].domain);
+}
+
+#[test]
+fn test_parse_www_authenticate_with_invalid_challenge() {
+    let value = "Digest realm=\"a\", nonce=\"abc\", Digest realm=\"invalid\"";
+    assert_eq!(Err(DigestError::InvalidHeader), parse_www_authenticate(value));
+}
+
+#[test]
+fn test_parse_www_authenticate_without_digest_challenge() {
+    assert_eq!(Err(DigestError::MissingSchemePrefix), parse_www_authenticate(""));
+    assert_eq!(
+        Err(DigestError::MissingSchemePrefix),
+        parse_www_authenticate("Basic realm=\"simple\"")
+    );
+}
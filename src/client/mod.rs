@@ -0,0 +1,138 @@
+// Copyright (c) 2017 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Client-side middleware which automatically answers `Digest` challenges, for use with
+//! [reqwest-middleware](https://crates.io/crates/reqwest-middleware).
+
+use async_trait::async_trait;
+use http::{Extensions, HeaderValue};
+use http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use reqwest::{Request, Response, StatusCode};
+use reqwest_middleware::{Error, Middleware, Next, Result};
+use std::fmt;
+use super::challenge::parse_challenges;
+use super::error::DigestError;
+use super::responder::ChallengeResponder;
+use zeroize::Zeroizing;
+
+pub use super::responder::{generate_cnonce, generate_cnonce_bytes};
+
+mod test;
+
+/// The maximum number of authenticated attempts per request: one in response to the initial
+/// challenge, and one in response to a `stale=true` challenge.
+const MAX_AUTHENTICATED_ATTEMPTS: usize = 2;
+
+/// Middleware which answers `401 Unauthorized` responses that carry a `Digest` challenge, by
+/// retrying the request with an `Authorization` header generated from its credentials.
+///
+/// Requests whose bodies cannot be cloned (i.e., streams) are sent as-is, since they cannot be
+/// retried. The password is zeroed when the middleware is dropped, and is not shown by `Debug`.
+#[derive(Clone)]
+pub struct DigestAuthMiddleware {
+    username: String,
+    password: Zeroizing<String>,
+}
+
+impl fmt::Debug for DigestAuthMiddleware {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DigestAuthMiddleware")
+            .field("username", &self.username)
+            .field("password", &"[redacted]")
+            .finish()
+    }
+}
+
+// Selects among all of the `Digest` challenges offered by the server, like `respond_to_401`.
+fn digest_responder(response: &Response) -> Option<ChallengeResponder> {
+    if response.status() != StatusCode::UNAUTHORIZED {
+        return None;
+    }
+    let challenges = response
+        .headers()
+        .get_all(WWW_AUTHENTICATE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(parse_challenges)
+        .collect();
+    ChallengeResponder::from_challenges(challenges).ok()
+}
+
+fn request_uri(request: &Request) -> String {
+    let url = request.url();
+    match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_owned(),
+    }
+}
+
+impl DigestAuthMiddleware {
+    /// Creates a middleware which authenticates with the given credentials.
+    pub fn new(username: String, password: String) -> DigestAuthMiddleware {
+        DigestAuthMiddleware {
+            username,
+            password: Zeroizing::new(password),
+        }
+    }
+
+    fn authorize(&self, request: &mut Request, mut responder: ChallengeResponder) -> Result<()> {
+        let method = request.method().as_str();
+        let uri = request_uri(request);
+        let entity_body = request.body().and_then(|body| body.as_bytes()).unwrap_or(b"");
+        let digest = responder
+            .generate_response(&self.username, &self.password, method, &uri, entity_body)
+            .map_err(Error::middleware)?;
+        let value = HeaderValue::from_str(&digest.to_string())
+            .map_err(|_| Error::middleware(DigestError::InvalidHeader))?;
+        request.headers_mut().insert(AUTHORIZATION, value);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Middleware for DigestAuthMiddleware {
+    async fn handle(
+        &self,
+        request: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let original = match request.try_clone() {
+            Some(original) => original,
+            None => return next.run(request, extensions).await,
+        };
+        let mut response = next.clone().run(request, extensions).await?;
+        for attempt in 0..MAX_AUTHENTICATED_ATTEMPTS {
+            let responder = match digest_responder(&response) {
+                Some(responder) => responder,
+                None => break,
+            };
+            // After the first attempt, only a stale nonce is worth retrying; any other challenge
+            // means that the credentials were rejected.
+            if attempt > 0 && !responder.challenge().stale {
+                break;
+            }
+            let mut retry = original.try_clone().expect("Request body was cloned before");
+            self.authorize(&mut retry, responder)?;
+            response = next.clone().run(retry, extensions).await?;
+        }
+        Ok(response)
+    }
+}
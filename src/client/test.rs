@@ -0,0 +1,223 @@
+// Copyright (c) 2017 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+#![cfg(test)]
+
+use reqwest::StatusCode;
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
+use super::DigestAuthMiddleware;
+use super::super::digest::parse_digest_header;
+use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+use wiremock::matchers::any;
+
+const USERNAME: &str = "Mufasa";
+const PASSWORD: &str = "Circle of Life";
+const REALM: &str = "http-auth@example.org";
+
+/// Matches requests with a valid `Digest` for the given server nonce.
+struct ValidDigest {
+    nonce: &'static str,
+}
+
+impl wiremock::Match for ValidDigest {
+    fn matches(&self, request: &Request) -> bool {
+        let digest = match request.headers.get("Authorization") {
+            Some(value) => parse_digest_header(value.as_bytes()),
+            None => return false,
+        };
+        match digest {
+            Ok(digest) => {
+                digest.username.as_bytes() == USERNAME.as_bytes() && digest.nonce == self.nonce &&
+                    digest.request_uri == "/dir/index.html?page=1" &&
+                    digest.response_matches_password(
+                        request.method.as_str(),
+                        &request.body,
+                        PASSWORD,
+                    )
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+fn challenge(nonce: &str, stale: bool) -> ResponseTemplate {
+    let mut challenge = format!(
+        "Digest realm=\"{}\", nonce=\"{}\", qop=\"auth\", algorithm=SHA-256",
+        REALM,
+        nonce
+    );
+    if stale {
+        challenge.push_str(", stale=true");
+    }
+    ResponseTemplate::new(401).insert_header("WWW-Authenticate", challenge.as_str())
+}
+
+fn client(password: &str) -> ClientWithMiddleware {
+    ClientBuilder::new(reqwest::Client::new())
+        .with(DigestAuthMiddleware::new(
+            USERNAME.to_owned(),
+            password.to_owned(),
+        ))
+        .build()
+}
+
+async fn mount_digest_server(server: &MockServer) {
+    Mock::given(ValidDigest { nonce: "7ypf/xlj9XXwfDPEoM4URrv" })
+        .respond_with(ResponseTemplate::new(200))
+        .with_priority(1)
+        .mount(server)
+        .await;
+    Mock::given(any())
+        .respond_with(challenge("7ypf/xlj9XXwfDPEoM4URrv", false))
+        .with_priority(2)
+        .mount(server)
+        .await;
+}
+
+async fn received_request_count(server: &MockServer) -> usize {
+    server.received_requests().await.map_or(0, |requests| requests.len())
+}
+
+#[tokio::test]
+async fn test_answers_digest_challenge() {
+    let server = MockServer::start().await;
+    mount_digest_server(&server).await;
+
+    let url = format!("{}/dir/index.html?page=1", server.uri());
+    let response = client(PASSWORD).get(&url).send().await.unwrap();
+    assert_eq!(StatusCode::OK, response.status());
+    assert_eq!(2, received_request_count(&server).await);
+}
+
+#[tokio::test]
+async fn test_answers_digest_challenge_for_request_with_body() {
+    let server = MockServer::start().await;
+    mount_digest_server(&server).await;
+
+    let url = format!("{}/dir/index.html?page=1", server.uri());
+    let response = client(PASSWORD).post(&url).body("Hakuna Matata").send().await.unwrap();
+    assert_eq!(StatusCode::OK, response.status());
+}
+
+#[tokio::test]
+async fn test_answers_strongest_digest_challenge() {
+    let server = MockServer::start().await;
+    Mock::given(ValidDigest { nonce: "7ypf/xlj9XXwfDPEoM4URrv" })
+        .respond_with(ResponseTemplate::new(200))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    let md5 = format!(
+        "Digest realm=\"{}\", nonce=\"OA6MG9tEQGm2hh\", qop=\"auth\", algorithm=MD5",
+        REALM
+    );
+    let sha256 = format!(
+        "Digest realm=\"{}\", nonce=\"7ypf/xlj9XXwfDPEoM4URrv\", qop=\"auth\", \
+         algorithm=SHA-256",
+        REALM
+    );
+    Mock::given(any())
+        .respond_with(
+            ResponseTemplate::new(401)
+                .append_header("WWW-Authenticate", md5.as_str())
+                .append_header("WWW-Authenticate", sha256.as_str()),
+        )
+        .with_priority(2)
+        .mount(&server)
+        .await;
+
+    let url = format!("{}/dir/index.html?page=1", server.uri());
+    let response = client(PASSWORD).get(&url).send().await.unwrap();
+    assert_eq!(StatusCode::OK, response.status());
+    assert_eq!(2, received_request_count(&server).await);
+}
+
+#[tokio::test]
+async fn test_returns_challenge_for_incorrect_password() {
+    let server = MockServer::start().await;
+    mount_digest_server(&server).await;
+
+    let url = format!("{}/dir/index.html?page=1", server.uri());
+    let response = client("Circle of Death").get(&url).send().await.unwrap();
+    assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+    assert_eq!(2, received_request_count(&server).await);
+}
+
+#[tokio::test]
+async fn test_retries_stale_nonce() {
+    let server = MockServer::start().await;
+    Mock::given(ValidDigest { nonce: "FQhe/qaU925kfnzjCev0ciny7Q" })
+        .respond_with(ResponseTemplate::new(200))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(ValidDigest { nonce: "7ypf/xlj9XXwfDPEoM4URrv" })
+        .respond_with(challenge("FQhe/qaU925kfnzjCev0ciny7Q", true))
+        .with_priority(2)
+        .mount(&server)
+        .await;
+    Mock::given(any())
+        .respond_with(challenge("7ypf/xlj9XXwfDPEoM4URrv", false))
+        .with_priority(3)
+        .mount(&server)
+        .await;
+
+    let url = format!("{}/dir/index.html?page=1", server.uri());
+    let response = client(PASSWORD).get(&url).send().await.unwrap();
+    assert_eq!(StatusCode::OK, response.status());
+    assert_eq!(3, received_request_count(&server).await);
+}
+
+#[tokio::test]
+async fn test_does_not_retry_without_challenge() {
+    let server = MockServer::start().await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&server)
+        .await;
+
+    let response = client(PASSWORD).get(server.uri()).send().await.unwrap();
+    assert_eq!(StatusCode::OK, response.status());
+    assert_eq!(1, received_request_count(&server).await);
+}
+
+#[tokio::test]
+async fn test_does_not_retry_other_scheme() {
+    let server = MockServer::start().await;
+    Mock::given(any())
+        .respond_with(
+            ResponseTemplate::new(401).insert_header("WWW-Authenticate", "Basic realm=\"Savanna\""),
+        )
+        .mount(&server)
+        .await;
+
+    let response = client(PASSWORD).get(server.uri()).send().await.unwrap();
+    assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+    assert_eq!(1, received_request_count(&server).await);
+}
+
+#[test]
+fn test_debug_redacts_password() {
+    let middleware = DigestAuthMiddleware::new(USERNAME.to_owned(), PASSWORD.to_owned());
+    let debug = format!("{:?}", middleware);
+    assert!(debug.contains(USERNAME));
+    assert!(debug.contains("[redacted]"));
+    assert!(!debug.contains(PASSWORD));
+}
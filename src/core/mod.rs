@@ -0,0 +1,528 @@
+// Copyright (c) 2017 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! The hashing and formatting logic of HTTP digest authentication, which only requires `alloc`,
+//! so that it can be used on `no_std` targets.
+
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::sync::{OnceLock, RwLock};
+use super::hash::{self, HashBackend};
+use unicode_normalization::UnicodeNormalization;
+use zeroize::Zeroizing;
+
+mod test;
+#[cfg(test)]
+pub mod test_helper;
+
+const HEX_CHARS: &[u8] = b"0123456789abcdef";
+
+/// The minimum security strength, in bits, that an algorithm should provide, which excludes the
+/// MD5 variants. See `HashAlgorithm::security_bits`.
+pub const RECOMMENDED_MINIMUM_SECURITY_BITS: u32 = 128;
+
+fn to_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        hex.push(HEX_CHARS[(byte >> 4) as usize] as char);
+        hex.push(HEX_CHARS[(byte & 0xf) as usize] as char);
+    }
+    hex
+}
+
+fn hex_value(digit: u8) -> Option<u8> {
+    HEX_CHARS.iter().position(|&c| c == digit).map(|value| value as u8)
+}
+
+// Custom hashers may generate anything, so their digests are validated rather than trusted.
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    let pairs = hex.as_bytes().chunks_exact(2);
+    if !pairs.remainder().is_empty() {
+        return None;
+    }
+    pairs
+        .map(|pair| Some((hex_value(pair[0])? << 4) | hex_value(pair[1])?))
+        .collect()
+}
+
+/// An incremental implementation of a hash function, which generates hexadecimal digests.
+///
+/// This is implemented for the built-in algorithms, and can be implemented by other crates to add
+/// algorithms via `HashAlgorithm::with_custom_hasher`, e.g. to use a FIPS-certified
+/// implementation.
+pub trait Hasher {
+    /// Adds `data` to the hash.
+    fn update(&mut self, data: &[u8]);
+    /// Consumes the hasher and returns the lowercase hexadecimal digest.
+    fn finalize(self: Box<Self>) -> String;
+}
+
+/// Creates `Hasher`s for a custom algorithm.
+pub type HasherFactory = Box<dyn Fn() -> Box<dyn Hasher> + Send + Sync>;
+
+struct BuiltinHasher {
+    hasher: hash::Hasher,
+    length: usize,
+}
+
+impl Hasher for BuiltinHasher {
+    fn update(&mut self, data: &[u8]) {
+        self.hasher.update(data);
+    }
+
+    fn finalize(self: Box<Self>) -> String {
+        let mut digest = self.hasher.finish();
+        digest.truncate(self.length);
+        to_hex(&digest)
+    }
+}
+
+/// Describes a custom algorithm for `register_algorithm`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CustomHashAlgorithm {
+    /// The name used in the `algorithm` parameter, e.g. `BLAKE3`.
+    pub name: &'static str,
+    /// The collision resistance of the algorithm, in bits. See `HashAlgorithm::security_bits`.
+    pub security_bits: u32,
+    /// Whether the algorithm is a session variant. See `HashAlgorithm::is_session`.
+    pub is_session: bool,
+}
+
+// Custom algorithms can only be registered with the `std` feature.
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+struct CustomAlgorithm {
+    security_bits: u32,
+    is_session: bool,
+    factory: HasherFactory,
+}
+
+#[cfg(feature = "std")]
+fn custom_algorithms() -> &'static RwLock<HashMap<String, CustomAlgorithm>> {
+    static CUSTOM_ALGORITHMS: OnceLock<RwLock<HashMap<String, CustomAlgorithm>>> =
+        OnceLock::new();
+    CUSTOM_ALGORITHMS.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+#[cfg(feature = "std")]
+fn with_custom_algorithm<T>(name: &str, f: impl FnOnce(&CustomAlgorithm) -> T) -> Option<T> {
+    let algorithms = custom_algorithms().read().unwrap_or_else(|error| error.into_inner());
+    algorithms.get(name).map(f)
+}
+
+#[cfg(not(feature = "std"))]
+fn with_custom_algorithm<T>(_name: &str, _f: impl FnOnce(&CustomAlgorithm) -> T) -> Option<T> {
+    None
+}
+
+#[cfg(feature = "std")]
+fn insert_custom_algorithm(name: &str, algorithm: CustomAlgorithm) -> HashAlgorithm {
+    if let Some(builtin) = HashAlgorithm::from_name(name) {
+        assert!(
+            !builtin.is_builtin(),
+            "Cannot replace the built-in algorithm {}",
+            name
+        );
+    }
+    let mut algorithms = custom_algorithms().write().unwrap_or_else(|error| error.into_inner());
+    algorithms.insert(name.to_owned(), algorithm);
+    HashAlgorithm::Custom(CustomAlgorithmName(name.to_owned()))
+}
+
+/// Registers a custom algorithm, whose hashes are generated by the hashers that `factory`
+/// creates, and returns it. Unlike `HashAlgorithm::with_custom_hasher`, this also specifies the
+/// algorithm's security strength and whether it is a session variant. Registering the same name
+/// again replaces the algorithm.
+///
+/// The registry is global, and requires the `std` feature.
+///
+/// # Panics
+///
+/// Panics if the name is the name of a built-in algorithm.
+#[cfg(feature = "std")]
+pub fn register_algorithm(algorithm: CustomHashAlgorithm, factory: HasherFactory) -> HashAlgorithm {
+    insert_custom_algorithm(
+        algorithm.name,
+        CustomAlgorithm {
+            security_bits: algorithm.security_bits,
+            is_session: algorithm.is_session,
+            factory,
+        },
+    )
+}
+
+/// The name of a registered custom algorithm, e.g. `BLAKE3`.
+///
+/// Names can only be obtained from `register_algorithm`, `HashAlgorithm::with_custom_hasher` and
+/// `HashAlgorithm::from_name`, so that every `HashAlgorithm::Custom` has a registered hasher.
+/// Registered algorithms can be replaced, but not removed.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct CustomAlgorithmName(String);
+
+impl CustomAlgorithmName {
+    /// The name used in the `algorithm` parameter.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for CustomAlgorithmName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Allowable hash algorithms for the `algorithm` parameter.
+///
+/// Algorithms are ordered by their security strength (see `security_bits`), so that e.g. `max()`
+/// returns the strongest algorithm offered by a server. Algorithms of the same strength are
+/// ordered by their variants, with custom algorithms after the built-in ones, by name. Since the
+/// strength of a custom algorithm is looked up in the registry, re-registering it with a different
+/// strength changes its order.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum HashAlgorithm {
+    /// `MD5`
+    #[cfg_attr(
+        not(feature = "allow-md5"),
+        deprecated(
+            since = "0.0.17",
+            note = "MD5 is cryptographically broken; use SHA-256 or SHA-512-256 instead"
+        )
+    )]
+    MD5,
+    /// `MD5-sess`
+    #[cfg_attr(
+        not(feature = "allow-md5"),
+        deprecated(
+            since = "0.0.17",
+            note = "MD5 is cryptographically broken; use SHA-256 or SHA-512-256 instead"
+        )
+    )]
+    MD5Session,
+    /// `SHA-256`
+    SHA256,
+    /// `SHA-256-sess`
+    SHA256Session,
+    /// `SHA-512-256`
+    SHA512256,
+    /// `SHA-512-256-sess`
+    SHA512256Session,
+    /// An algorithm registered via `HashAlgorithm::with_custom_hasher` or `register_algorithm`,
+    /// e.g. `BLAKE3`.
+    Custom(CustomAlgorithmName),
+}
+
+/// The algorithm to use when the `algorithm` parameter is absent, as specified in
+/// [RFC 7616, section 3.3](https://tools.ietf.org/html/rfc7616#section-3.3) (and
+/// [RFC 2617, section 3.2.1](https://tools.ietf.org/html/rfc2617#section-3.2.1)).
+#[allow(deprecated)]
+impl Default for HashAlgorithm {
+    fn default() -> HashAlgorithm {
+        HashAlgorithm::MD5
+    }
+}
+
+impl Ord for HashAlgorithm {
+    fn cmp(&self, other: &HashAlgorithm) -> Ordering {
+        self.security_bits()
+            .cmp(&other.security_bits())
+            .then_with(|| self.variant_index().cmp(&other.variant_index()))
+            .then_with(|| match (self, other) {
+                (HashAlgorithm::Custom(a), HashAlgorithm::Custom(b)) => a.cmp(b),
+                _ => Ordering::Equal,
+            })
+    }
+}
+
+impl PartialOrd for HashAlgorithm {
+    fn partial_cmp(&self, other: &HashAlgorithm) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[allow(deprecated)]
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HashAlgorithm::MD5 => write!(f, "MD5"),
+            HashAlgorithm::MD5Session => write!(f, "MD5-sess"),
+            HashAlgorithm::SHA256 => write!(f, "SHA-256"),
+            HashAlgorithm::SHA256Session => write!(f, "SHA-256-sess"),
+            HashAlgorithm::SHA512256 => write!(f, "SHA-512-256"),
+            HashAlgorithm::SHA512256Session => write!(f, "SHA-512-256-sess"),
+            HashAlgorithm::Custom(ref name) => name.fmt(f),
+        }
+    }
+}
+
+#[allow(deprecated)]
+impl HashAlgorithm {
+    /// Looks up an algorithm by the name used in the `algorithm` parameter, e.g. `SHA-256`,
+    /// including the names registered via `with_custom_hasher`.
+    pub fn from_name(name: &str) -> Option<HashAlgorithm> {
+        match name {
+            "MD5" => Some(HashAlgorithm::MD5),
+            "MD5-sess" => Some(HashAlgorithm::MD5Session),
+            "SHA-256" => Some(HashAlgorithm::SHA256),
+            "SHA-256-sess" => Some(HashAlgorithm::SHA256Session),
+            "SHA-512-256" => Some(HashAlgorithm::SHA512256),
+            "SHA-512-256-sess" => Some(HashAlgorithm::SHA512256Session),
+            _ => {
+                with_custom_algorithm(name, |_| {
+                    HashAlgorithm::Custom(CustomAlgorithmName(name.to_owned()))
+                })
+            }
+        }
+    }
+
+    /// Registers a custom algorithm named `name`, whose hashes are generated by the hashers that
+    /// `factory` creates, and returns it. The algorithm is then parsed from headers like the
+    /// built-in ones. Registering the same name again replaces the algorithm.
+    ///
+    /// The algorithm is not a session variant, and its security strength is unknown (0 bits); use
+    /// `register_algorithm` to specify them. The registry is global, and requires the `std`
+    /// feature.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `name` is the name of a built-in algorithm.
+    #[cfg(feature = "std")]
+    pub fn with_custom_hasher(name: &str, factory: HasherFactory) -> HashAlgorithm {
+        insert_custom_algorithm(
+            name,
+            CustomAlgorithm {
+                security_bits: 0,
+                is_session: false,
+                factory,
+            },
+        )
+    }
+
+    fn variant_index(&self) -> u8 {
+        match *self {
+            HashAlgorithm::MD5 => 0,
+            HashAlgorithm::MD5Session => 1,
+            HashAlgorithm::SHA256 => 2,
+            HashAlgorithm::SHA256Session => 3,
+            HashAlgorithm::SHA512256 => 4,
+            HashAlgorithm::SHA512256Session => 5,
+            HashAlgorithm::Custom(_) => 6,
+        }
+    }
+
+    fn is_builtin(&self) -> bool {
+        !matches!(*self, HashAlgorithm::Custom(_))
+    }
+
+    fn builtin_hasher(function: hash::Function, length: usize) -> Box<dyn Hasher> {
+        Box::new(BuiltinHasher {
+            hasher: hash::Hasher::new(function),
+            length,
+        })
+    }
+
+    /// Creates an incremental hasher for the algorithm.
+    pub fn hasher(&self) -> Box<dyn Hasher> {
+        match *self {
+            HashAlgorithm::MD5 |
+            HashAlgorithm::MD5Session => HashAlgorithm::builtin_hasher(hash::Function::MD5, 16),
+            HashAlgorithm::SHA256 |
+            HashAlgorithm::SHA256Session => {
+                HashAlgorithm::builtin_hasher(hash::Function::SHA256, 32)
+            }
+            HashAlgorithm::SHA512256 |
+            HashAlgorithm::SHA512256Session => {
+                HashAlgorithm::builtin_hasher(hash::Function::SHA512, 32)
+            }
+            HashAlgorithm::Custom(ref name) => {
+                with_custom_algorithm(name.as_str(), |algorithm| (algorithm.factory)())
+                    .expect("Custom algorithm names are only created for registered algorithms")
+            }
+        }
+    }
+
+    /// Generate the raw output of a cryptographic hash function, given `data` and the algorithm.
+    ///
+    /// Returns `None` if the algorithm is a custom algorithm whose hasher did not generate a
+    /// lowercase hexadecimal digest.
+    pub fn digest(&self, data: &[u8]) -> Option<Vec<u8>> {
+        from_hex(&self.hex_digest(data))
+    }
+
+    /// Generate a hexadecimal representation of the output of a cryptographic hash function, given
+    /// `data` and the algorithm.
+    pub fn hex_digest(&self, data: &[u8]) -> String {
+        let mut hasher = self.hasher();
+        hasher.update(data);
+        hasher.finalize()
+    }
+
+    /// Whether the algorithm is a session variant, i.e. whether A1 includes the nonce and the
+    /// client nonce.
+    pub fn is_session(&self) -> bool {
+        match *self {
+            HashAlgorithm::MD5Session |
+            HashAlgorithm::SHA256Session |
+            HashAlgorithm::SHA512256Session => true,
+            HashAlgorithm::Custom(ref name) => {
+                with_custom_algorithm(name.as_str(), |algorithm| algorithm.is_session)
+                    .unwrap_or(false)
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the algorithm is deprecated, i.e. whether it is based on MD5, which
+    /// [RFC 7616, section 3.3](https://tools.ietf.org/html/rfc7616#section-3.3) only supports for
+    /// backward compatibility with RFC 2617.
+    pub fn is_deprecated(&self) -> bool {
+        matches!(*self, HashAlgorithm::MD5 | HashAlgorithm::MD5Session)
+    }
+
+    /// Whether the algorithm is based on a SHA-2 hash function (SHA-256 or SHA-512/256), as opposed
+    /// to MD5 or a custom algorithm.
+    pub fn is_sha2_family(&self) -> bool {
+        !self.is_deprecated() && self.is_builtin()
+    }
+
+    /// The collision resistance of the algorithm, in bits: 64 for the MD5 variants (generously,
+    /// since practical collision attacks exist), and 128 for the SHA-256 and SHA-512-256 variants.
+    /// Custom algorithms have the strength they were registered with via `register_algorithm`,
+    /// or 0 if it is unknown.
+    pub fn security_bits(&self) -> u32 {
+        if let HashAlgorithm::Custom(ref name) = *self {
+            with_custom_algorithm(name.as_str(), |algorithm| algorithm.security_bits)
+                .unwrap_or(0)
+        } else if self.is_sha2_family() {
+            128
+        } else {
+            64
+        }
+    }
+
+    /// The length of a hexadecimal digest generated by the algorithm.
+    pub fn digest_hex_length(&self) -> usize {
+        match *self {
+            HashAlgorithm::MD5 |
+            HashAlgorithm::MD5Session => 32,
+            HashAlgorithm::SHA256 |
+            HashAlgorithm::SHA256Session |
+            HashAlgorithm::SHA512256 |
+            HashAlgorithm::SHA512256Session => 64,
+            HashAlgorithm::Custom(_) => self.hex_digest(b"").len(),
+        }
+    }
+}
+
+/// Allowable values for the `qop`, or "quality of protection" parameter.
+///
+/// `Qop` intentionally does not implement `Default`: the RFCs do not define a default value, and
+/// an absent `qop` means RFC 2069 compatibility mode, which is represented as `None`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Qop {
+    /// `auth`
+    Auth,
+    /// `auth-int`
+    AuthInt,
+}
+
+impl fmt::Display for Qop {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Qop::Auth => write!(f, "auth"),
+            Qop::AuthInt => write!(f, "auth-int"),
+        }
+    }
+}
+
+/// Generates the hexadecimal digest of `value`, i.e. `H(value)`, as defined in
+/// [RFC 7616, section 3.4.1](https://tools.ietf.org/html/rfc7616#section-3.4.1) (and
+/// [RFC 2617, section 3.2.1](https://tools.ietf.org/html/rfc2617#section-3.2.1)), which is the
+/// primitive that A1, A2 and `KD` are built on.
+///
+/// This is also re-exported as `guardhaus::digest_hash`, for protocols which reuse the same hash
+/// primitives.
+pub fn hash_value(algorithm: &HashAlgorithm, value: &[u8]) -> String {
+    algorithm.hex_digest(value)
+}
+
+/// Normalizes a username or password to Unicode Normalization Form C (NFC), as required by
+/// [RFC 7616, section 3.4.1](https://tools.ietf.org/html/rfc7616#section-3.4.1) before computing
+/// the A1 value when the `charset` is `UTF-8`.
+pub fn normalize_for_a1(s: &str) -> String {
+    s.nfc().collect()
+}
+
+/// Generates a simple A1 value, i.e. `username:realm:password`, which is the A1 value of the
+/// non-session algorithms. The value contains the plaintext password, so it is zeroed when
+/// dropped.
+pub fn generate_simple_a1(username: &[u8], realm: &str, password: &str) -> Zeroizing<Vec<u8>> {
+    let capacity = username.len() + realm.len() + password.len() + 2;
+    let mut a1 = Zeroizing::new(Vec::with_capacity(capacity));
+    a1.extend_from_slice(username);
+    a1.push(b':');
+    a1.extend_from_slice(realm.as_bytes());
+    a1.push(b':');
+    a1.extend_from_slice(password.as_bytes());
+    a1
+}
+
+/// Generates the hexadecimal digest of a simple A1 value, i.e. `H(username:realm:password)`, as
+/// defined in [RFC 7616, section 3.4.2](https://tools.ietf.org/html/rfc7616#section-3.4.2).
+pub fn generate_simple_hashed_a1(
+    algorithm: &HashAlgorithm,
+    username: &[u8],
+    realm: &str,
+    password: &str,
+) -> String {
+    hash_value(algorithm, &generate_simple_a1(username, realm, password))
+}
+
+/// Generates the hexadecimal keyed digest `KD(secret, data)`, i.e. `H(secret:data)`, as defined in
+/// [RFC 7616, section 3.4.1](https://tools.ietf.org/html/rfc7616#section-3.4.1).
+pub fn generate_kd(algorithm: &HashAlgorithm, secret: &str, data: &str) -> String {
+    let mut value = String::with_capacity(secret.len() + data.len() + 1);
+    value.push_str(secret);
+    value.push(':');
+    value.push_str(data);
+    hash_value(algorithm, value.as_bytes())
+}
+
+/// Append a header parameter to a serialized header.
+pub fn append_parameter(serialized: &mut String, key: &str, value: &str, quoted: bool) {
+    if !serialized.is_empty() {
+        serialized.push_str(", ")
+    }
+    serialized.push_str(key);
+    serialized.push('=');
+    if quoted {
+        serialized.push('"');
+    }
+    serialized.push_str(value);
+    if quoted {
+        serialized.push('"');
+    }
+}
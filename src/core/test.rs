@@ -0,0 +1,251 @@
+// Copyright (c) 2017 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+#![cfg(test)]
+
+#[cfg(feature = "std")]
+use alloc::boxed::Box;
+use super::{HashAlgorithm, append_parameter, generate_kd, generate_simple_a1,
+            generate_simple_hashed_a1, hash_value, normalize_for_a1};
+#[cfg(feature = "std")]
+use super::{CustomHashAlgorithm, register_algorithm};
+#[cfg(feature = "std")]
+use super::test_helper::{FixedHasher, ReversedSha256};
+
+#[test]
+fn test_hash_value() {
+    assert_eq!(
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+        hash_value(&HashAlgorithm::SHA256, b"")
+    );
+    assert_eq!(64, hash_value(&HashAlgorithm::SHA512256, b"").len());
+}
+
+#[test]
+fn test_generate_simple_a1() {
+    assert_eq!(
+        b"Mufasa:http-auth@example.org:Circle of Life".to_vec(),
+        *generate_simple_a1(b"Mufasa", "http-auth@example.org", "Circle of Life")
+    );
+}
+
+#[test]
+fn test_generate_simple_hashed_a1() {
+    assert_eq!(
+        "7987c64c30e25f1b74be53f966b49b90f2808aa92faf9a00262392d7b4794232",
+        generate_simple_hashed_a1(
+            &HashAlgorithm::SHA256,
+            b"Mufasa",
+            "http-auth@example.org",
+            "Circle of Life",
+        )
+    );
+}
+
+#[test]
+fn test_generate_kd() {
+    let a1 = "7987c64c30e25f1b74be53f966b49b90f2808aa92faf9a00262392d7b4794232";
+    let data = "7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v:00000001:\
+                f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ:auth:\
+                9a3fdae9a622fe8de177c24fa9c070f2b181ec85e15dcbdc32e10c82ad450b04";
+    assert_eq!(
+        "753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1",
+        generate_kd(&HashAlgorithm::SHA256, a1, data)
+    );
+}
+
+#[test]
+fn test_append_parameter() {
+    let mut serialized = String::new();
+    append_parameter(&mut serialized, "realm", "http-auth@example.org", true);
+    append_parameter(&mut serialized, "algorithm", "SHA-256", false);
+    assert_eq!(
+        "realm=\"http-auth@example.org\", algorithm=SHA-256",
+        serialized
+    );
+}
+
+#[allow(deprecated)]
+#[test]
+fn test_digest_hash() {
+    use crate::digest_hash;
+
+    let md5_empty = "d41d8cd98f00b204e9800998ecf8427e";
+    let md5_abc = "900150983cd24fb0d6963f7d28e17f72";
+    let sha256_empty = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+    let sha256_abc = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+    // SHA-512-256 is computed as SHA-512, truncated to 256 bits.
+    let sha512256_empty = "cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce";
+    let sha512256_abc = "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a";
+    let cases = [
+        (HashAlgorithm::MD5, md5_empty, md5_abc),
+        (HashAlgorithm::MD5Session, md5_empty, md5_abc),
+        (HashAlgorithm::SHA256, sha256_empty, sha256_abc),
+        (HashAlgorithm::SHA256Session, sha256_empty, sha256_abc),
+        (HashAlgorithm::SHA512256, sha512256_empty, sha512256_abc),
+        (HashAlgorithm::SHA512256Session, sha512256_empty, sha512256_abc),
+    ];
+    for (algorithm, empty, abc) in cases.iter() {
+        assert_eq!(*empty, digest_hash(algorithm, b""), "{}", algorithm);
+        assert_eq!(*abc, digest_hash(algorithm, b"abc"), "{}", algorithm);
+    }
+}
+
+#[allow(deprecated)]
+#[test]
+fn test_hash_algorithm_ordering() {
+    let ascending = [
+        HashAlgorithm::MD5,
+        HashAlgorithm::MD5Session,
+        HashAlgorithm::SHA256,
+        HashAlgorithm::SHA256Session,
+        HashAlgorithm::SHA512256,
+        HashAlgorithm::SHA512256Session,
+    ];
+    for (i, a) in ascending.iter().enumerate() {
+        for (j, b) in ascending.iter().enumerate() {
+            assert_eq!(i.cmp(&j), a.cmp(b), "{} <=> {}", a, b);
+            assert_eq!(Some(i.cmp(&j)), a.partial_cmp(b), "{} <=> {}", a, b);
+            for (k, c) in ascending.iter().enumerate() {
+                if i < j && j < k {
+                    assert!(a < b && b < c && a < c, "{} < {} < {}", a, b, c);
+                }
+            }
+        }
+    }
+
+    let offered = [HashAlgorithm::MD5Session, HashAlgorithm::SHA256, HashAlgorithm::MD5];
+    assert_eq!(Some(&HashAlgorithm::SHA256), offered.iter().max());
+    assert_eq!(Some(&HashAlgorithm::MD5), offered.iter().min());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_hash_algorithm_ordering_with_registered_algorithm() {
+    let custom = CustomHashAlgorithm {
+        name: "REVERSED-SHA-256-256-BITS",
+        security_bits: 256,
+        is_session: false,
+    };
+    let algorithm = register_algorithm(custom, Box::new(ReversedSha256::boxed));
+    assert!(HashAlgorithm::SHA512256Session < algorithm);
+    let offered = [HashAlgorithm::SHA512256, algorithm.clone(), HashAlgorithm::SHA256];
+    assert_eq!(Some(&algorithm), offered.iter().max());
+}
+
+#[test]
+fn test_hasher() {
+    let mut hasher = HashAlgorithm::SHA256.hasher();
+    hasher.update(b"a");
+    hasher.update(b"bc");
+    assert_eq!(hash_value(&HashAlgorithm::SHA256, b"abc"), hasher.finalize());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_with_custom_hasher() {
+    let algorithm =
+        HashAlgorithm::with_custom_hasher("REVERSED-SHA-256", Box::new(ReversedSha256::boxed));
+    match algorithm {
+        HashAlgorithm::Custom(ref name) => assert_eq!("REVERSED-SHA-256", name.as_str()),
+        _ => panic!("Not a custom algorithm: {}", algorithm),
+    }
+    assert_eq!(Some(algorithm.clone()), HashAlgorithm::from_name("REVERSED-SHA-256"));
+    assert_eq!("REVERSED-SHA-256", algorithm.to_string());
+    assert_eq!(
+        "da51002f16ff014bc9a771693a16300b3222ead5ed041414aefc10f8fb6187ab",
+        algorithm.hex_digest(b"abc")
+    );
+    assert_eq!(Some(32), algorithm.digest(b"abc").map(|digest| digest.len()));
+    assert_eq!(64, algorithm.digest_hex_length());
+    assert!(!algorithm.is_session());
+    assert!(!algorithm.is_sha2_family());
+    assert!(!algorithm.is_deprecated());
+    assert_eq!(0, algorithm.security_bits());
+    assert!(algorithm < HashAlgorithm::SHA256);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_register_algorithm() {
+    let custom = CustomHashAlgorithm {
+        name: "REVERSED-SHA-256-sess",
+        security_bits: 128,
+        is_session: true,
+    };
+    let algorithm = register_algorithm(custom, Box::new(ReversedSha256::boxed));
+    match algorithm {
+        HashAlgorithm::Custom(ref name) => assert_eq!("REVERSED-SHA-256-sess", name.as_str()),
+        _ => panic!("Not a custom algorithm: {}", algorithm),
+    }
+    assert_eq!(Some(algorithm.clone()), HashAlgorithm::from_name("REVERSED-SHA-256-sess"));
+    assert_eq!("REVERSED-SHA-256-sess", algorithm.to_string());
+    assert_eq!(
+        "da51002f16ff014bc9a771693a16300b3222ead5ed041414aefc10f8fb6187ab",
+        algorithm.hex_digest(b"abc")
+    );
+    assert!(algorithm.is_session());
+    assert_eq!(128, algorithm.security_bits());
+    assert!(!algorithm.is_sha2_family());
+    assert!(!algorithm.is_deprecated());
+}
+
+#[cfg(feature = "std")]
+#[test]
+#[should_panic(expected = "Cannot replace the built-in algorithm SHA-512-256")]
+fn test_register_algorithm_with_builtin_name() {
+    let custom = CustomHashAlgorithm {
+        name: "SHA-512-256",
+        security_bits: 256,
+        is_session: false,
+    };
+    register_algorithm(custom, Box::new(ReversedSha256::boxed));
+}
+
+#[test]
+fn test_from_name_with_unregistered_algorithm() {
+    assert_eq!(None, HashAlgorithm::from_name("BLAKE3"));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_digest_with_invalid_custom_hex_digest() {
+    let invalid = [("ODD-LENGTH-HASH", "abc"), ("NON-ASCII-HASH", "aé"), ("UPPERCASE-HASH", "AB")];
+    for &(name, hex_digest) in &invalid {
+        let factory = Box::new(move || FixedHasher::boxed(hex_digest));
+        let algorithm = HashAlgorithm::with_custom_hasher(name, factory);
+        assert_eq!(hex_digest, algorithm.hex_digest(b"abc"));
+        assert_eq!(None, algorithm.digest(b"abc"));
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+#[should_panic(expected = "Cannot replace the built-in algorithm SHA-256")]
+fn test_with_custom_hasher_with_builtin_name() {
+    HashAlgorithm::with_custom_hasher("SHA-256", Box::new(ReversedSha256::boxed));
+}
+
+#[test]
+fn test_normalize_for_a1() {
+    assert_eq!("J\u{e4}s\u{f8}n Doe", normalize_for_a1("Ja\u{308}s\u{f8}n Doe"));
+    assert_eq!("J\u{e4}s\u{f8}n Doe", normalize_for_a1("J\u{e4}s\u{f8}n Doe"));
+    assert_eq!("Circle of Life", normalize_for_a1("Circle of Life"));
+}
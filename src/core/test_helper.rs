@@ -0,0 +1,61 @@
+// Copyright (c) 2017 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+#![allow(dead_code, missing_docs)]
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use super::{HashAlgorithm, Hasher};
+
+/// Reverses the hexadecimal SHA-256 digest, so that it is distinguishable from SHA-256.
+pub struct ReversedSha256(Box<dyn Hasher>);
+
+impl ReversedSha256 {
+    pub fn boxed() -> Box<dyn Hasher> {
+        Box::new(ReversedSha256(HashAlgorithm::SHA256.hasher()))
+    }
+}
+
+impl Hasher for ReversedSha256 {
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    fn finalize(self: Box<Self>) -> String {
+        self.0.finalize().chars().rev().collect()
+    }
+}
+
+/// Generates the same (not necessarily hexadecimal) digest for all data.
+pub struct FixedHasher(&'static str);
+
+impl FixedHasher {
+    pub fn boxed(digest: &'static str) -> Box<dyn Hasher> {
+        Box::new(FixedHasher(digest))
+    }
+}
+
+impl Hasher for FixedHasher {
+    fn update(&mut self, _data: &[u8]) {}
+
+    fn finalize(self: Box<Self>) -> String {
+        self.0.into()
+    }
+}
@@ -0,0 +1,87 @@
+// Copyright (c) 2017 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Extracting `Digest` headers in [axum](https://crates.io/crates/axum) handlers.
+
+use axum::extract::FromRequestParts;
+use axum::response::{IntoResponse, Response};
+use http::StatusCode;
+use http::header::AUTHORIZATION;
+use http::request::Parts;
+use std::error::Error;
+use std::fmt;
+use std::future::{self, Future};
+use super::{Digest, parse_digest_header};
+use super::super::error::DigestError;
+
+/// The rejection returned when a `Digest` cannot be extracted from a request.
+///
+/// Both variants respond with `400 Bad Request`. Handlers that need to respond with a
+/// `WWW-Authenticate` challenge instead should extract `Result<Digest, DigestRejection>`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DigestRejection {
+    /// The request has no `Authorization` header.
+    MissingHeader,
+    /// The `Authorization` header is not a valid `Digest` header.
+    InvalidHeader(DigestError),
+}
+
+impl fmt::Display for DigestRejection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DigestRejection::MissingHeader => write!(f, "Missing Authorization header"),
+            DigestRejection::InvalidHeader(ref err) => {
+                write!(f, "Invalid Authorization header: {}", err)
+            }
+        }
+    }
+}
+
+impl Error for DigestRejection {}
+
+impl IntoResponse for DigestRejection {
+    fn into_response(self) -> Response {
+        (StatusCode::BAD_REQUEST, self.to_string()).into_response()
+    }
+}
+
+/// Parses the `Authorization` header of the request, and sets the `Digest`'s method to the
+/// method of the request.
+impl<S: Send + Sync> FromRequestParts<S> for Digest {
+    type Rejection = DigestRejection;
+
+    fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> impl Future<Output = Result<Self, Self::Rejection>> + Send {
+        let result = match parts.headers.get(AUTHORIZATION) {
+            Some(value) => {
+                parse_digest_header(value.as_bytes())
+                    .map(|mut digest| {
+                        digest.method = Some(parts.method.as_str().to_owned());
+                        digest
+                    })
+                    .map_err(DigestRejection::InvalidHeader)
+            }
+            None => Err(DigestRejection::MissingHeader),
+        };
+        future::ready(result)
+    }
+}
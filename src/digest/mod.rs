@@ -20,19 +20,47 @@
 
 //! An HTTP Digest implementation for [Hyper](http://hyper.rs)'s `Authorization` header.
 
-use hyper::Method;
+#[cfg(feature = "http")]
+use http::{HeaderMap, HeaderValue as HttpHeaderValue};
+#[cfg(feature = "http")]
+use http::header::AUTHORIZATION;
 use hyper::error::Error;
-use hyper::header::{Charset, Scheme};
+use hyper::header::{Authorization, Charset, Scheme};
 use hyper::header::parsing::{ExtendedValue, parse_extended_value};
-use parsing::{append_parameter, parse_parameters, unraveled_map_value};
-use std::collections::HashMap;
+#[cfg(feature = "hyper1")]
+use hyper1::header::HeaderValue;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+#[cfg(feature = "serde")]
+use serde::de::Error as DeError;
+#[cfg(feature = "http")]
+use std::convert::TryFrom;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::str::FromStr;
-use super::types::{HashAlgorithm, NonceCount, Qop};
+use super::challenge::Challenge;
+use super::core::{generate_kd, generate_simple_a1, generate_simple_hashed_a1, normalize_for_a1};
+use super::error::{DigestError, ValidationFailure, ValidationOutcome};
+#[cfg(feature = "debug-validation")]
+use super::error::DigestResponseMismatchError;
+use super::parsing::{append_parameter, get_param, has_whitespace_around_equals, is_quoted,
+                     parse_parameters, strip_scheme, unraveled_param_value};
+use super::types::{HashAlgorithm, NonceCount, Qop, parse_nonce_count};
 use unicase::UniCase;
+use url::Url;
+use zeroize::Zeroizing;
 
+#[cfg(feature = "axum")]
+mod extract;
+mod rfc7616_vectors;
 mod test;
-mod test_helper;
+mod test_case;
+#[cfg(test)]
+pub mod test_helper;
+
+#[cfg(feature = "axum")]
+pub use self::extract::DigestRejection;
+pub use self::test_case::generate_digest_test_case;
 
 /// Represents a `username` (or user hash, if the header's `userhash` parameter is `true`).
 #[derive(Clone, Debug, PartialEq)]
@@ -44,6 +72,87 @@ pub enum Username {
     Encoded(ExtendedValue),
 }
 
+impl Username {
+    /// The raw bytes of the username (or userhash). For an RFC 5987-encoded username, these are
+    /// the decoded bytes of the value.
+    pub fn as_bytes(&self) -> &[u8] {
+        match *self {
+            Username::Plain(ref username) => username.as_bytes(),
+            Username::Encoded(ref encoded) => &encoded.value,
+        }
+    }
+
+    /// Converts the username into its raw bytes. See `as_bytes` for details.
+    pub fn into_bytes(self) -> Vec<u8> {
+        match self {
+            Username::Plain(username) => username.into_bytes(),
+            Username::Encoded(encoded) => encoded.value,
+        }
+    }
+
+    /// A log-safe representation of the username, which only shows its first character, e.g.
+    /// `[redacted@M...]`.
+    ///
+    /// Unlike `display_redacted`, the standard `Display` implementation shows the full username.
+    pub fn display_redacted<'a>(&'a self) -> impl fmt::Display + 'a {
+        RedactedUsername(self)
+    }
+
+    /// The username as a string, suitable for display. Returns `None` for an RFC 5987-encoded
+    /// username, as its bytes are not necessarily valid UTF-8.
+    pub fn as_display_string(&self) -> Option<&str> {
+        match *self {
+            Username::Plain(ref username) => Some(username),
+            Username::Encoded(_) => None,
+        }
+    }
+
+    /// The human-readable username. An RFC 5987-encoded username is percent-decoded and
+    /// interpreted as UTF-8, e.g. `UTF-8''J%C3%A4s%C3%B8n%20Doe` becomes `Jäsøn Doe`, unlike the
+    /// standard `Display` implementation, which shows the encoded form.
+    pub fn to_display_string(&self) -> Result<String, DigestError> {
+        match *self {
+            Username::Plain(ref username) => Ok(username.clone()),
+            Username::Encoded(ref encoded) => {
+                String::from_utf8(encoded.value.clone())
+                    .map_err(|_| DigestError::InvalidUtf8InUsername)
+            }
+        }
+    }
+}
+
+struct RedactedUsername<'a>(&'a Username);
+
+impl Eq for Username {}
+
+// `ExtendedValue` does not implement `Hash`, so encoded usernames are hashed by their decoded
+// bytes, which is consistent with `PartialEq`.
+impl Hash for Username {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match *self {
+            Username::Plain(ref username) => {
+                0u8.hash(state);
+                username.hash(state)
+            }
+            Username::Encoded(ref encoded) => {
+                1u8.hash(state);
+                encoded.value.hash(state)
+            }
+        }
+    }
+}
+
+impl<'a> fmt::Display for RedactedUsername<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match String::from_utf8_lossy(self.0.as_bytes()).chars().next() {
+            Some(first) => write!(f, "[redacted@{}...]", first),
+            None => write!(f, "[redacted]"),
+        }
+    }
+}
+
+/// Shows the full username (or for encoded usernames, the RFC 5987 encoding). See
+/// `Username::display_redacted` for a log-safe alternative.
 impl fmt::Display for Username {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
@@ -62,6 +171,9 @@ impl fmt::Display for Username {
 pub struct Digest {
     /// Either the user name or the user hash (if `userhash` is `true` - see [RFC 7616, section
     /// 3.4.4](https://tools.ietf.org/html/rfc7616#section-3.4.4)).
+    ///
+    /// An empty user name is valid, but carries no user identity. Some servers use it for
+    /// anonymous access with limited privileges (see `is_anonymous`).
     pub username: Username,
     /// Authentication realm.
     pub realm: String,
@@ -85,6 +197,20 @@ pub struct Digest {
     pub charset: Option<Charset>,
     /// Whether `username` is a userhash. Added for RFC 7616.
     pub userhash: bool,
+    /// The method of the HTTP request, e.g. `GET`. This is not a header parameter, so it is never
+    /// serialized, and is `None` when parsed.
+    pub method: Option<String>,
+}
+
+impl Eq for Digest {}
+
+/// Only `response` is hashed, since it identifies a single authentication attempt, so that e.g. a
+/// `HashSet<Digest>` can cheaply detect retried requests. This is consistent with `PartialEq`, as
+/// equal `Digest`s have equal responses.
+impl Hash for Digest {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.response.hash(state)
+    }
 }
 
 impl Scheme for Digest {
@@ -93,7 +219,637 @@ impl Scheme for Digest {
     }
 
     fn fmt_scheme(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut serialized = String::new();
+        write!(f, "{}", self.serialized_parameters())
+    }
+}
+
+/// Formats the full `Authorization` header value, including the `Digest` scheme, for use outside
+/// of hyper's `Authorization` header.
+impl fmt::Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Digest {}", self.serialized_parameters())
+    }
+}
+
+/// Limits on the lengths of `Digest` header fields, and how strictly the header syntax is checked,
+/// for parsing headers from untrusted clients via `Digest::from_str_with_config`. Lengths are in
+/// bytes.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseConfig {
+    /// The maximum length of the `username` (or the decoded `username*`) parameter.
+    pub max_username_len: usize,
+    /// The maximum length of the `realm` parameter.
+    pub max_realm_len: usize,
+    /// The maximum length of the `nonce` parameter.
+    pub max_nonce_len: usize,
+    /// The maximum length of the `opaque` parameter.
+    pub max_opaque_len: usize,
+    /// The maximum length of the header parameters as a whole.
+    pub max_total_header_len: usize,
+    /// Whether headers which `from_str` tolerates, but which deviate from the RFCs, are rejected:
+    /// whitespace around the `=` of a parameter, and uppercase hexadecimal digits in `nc`. If
+    /// `false`, the names of the built-in algorithms are also matched case-insensitively, e.g.
+    /// `algorithm=md5`.
+    pub strict: bool,
+}
+
+impl ParseConfig {
+    /// The default length limits, rejecting headers which deviate from the RFCs.
+    pub fn strict() -> ParseConfig {
+        ParseConfig {
+            max_username_len: 1024,
+            max_realm_len: 1024,
+            max_nonce_len: 1024,
+            max_opaque_len: 1024,
+            max_total_header_len: 8192,
+            strict: true,
+        }
+    }
+
+    /// The default length limits, accepting slightly malformed headers which some clients send.
+    pub fn lenient() -> ParseConfig {
+        ParseConfig {
+            strict: false,
+            ..ParseConfig::strict()
+        }
+    }
+}
+
+/// Strict parsing, with 1024 bytes for each field and 8192 bytes for the whole header, which is
+/// the default header size limit of many HTTP servers.
+impl Default for ParseConfig {
+    fn default() -> ParseConfig {
+        ParseConfig::strict()
+    }
+}
+
+/// Prepares a password via SASLprep ([RFC 4013](https://tools.ietf.org/html/rfc4013)), which maps
+/// e.g. non-ASCII spaces to spaces and applies NFKC, and rejects prohibited characters.
+#[cfg(feature = "saslprep")]
+fn prepare_password(password: &str) -> Result<Zeroizing<String>, DigestError> {
+    match stringprep::saslprep(password) {
+        Ok(prepared) => Ok(Zeroizing::new(prepared.into_owned())),
+        Err(_) => Err(DigestError::InvalidPassword),
+    }
+}
+
+#[cfg(not(feature = "saslprep"))]
+fn prepare_password(password: &str) -> Result<Zeroizing<String>, DigestError> {
+    Ok(Zeroizing::new(password.to_owned()))
+}
+
+// Rejects the deviations from the RFCs that are tolerated by the parser, as described by
+// `ParseConfig::strict`.
+fn check_strict_syntax(s: &str) -> Result<(), DigestError> {
+    if has_whitespace_around_equals(s) {
+        return Err(DigestError::InvalidHeader);
+    }
+    let params = parse_parameters(s).map_err(|_| DigestError::InvalidHeader)?;
+    match unraveled_param_value(&params, "nc") {
+        Some(ref nc) if nc.chars().any(|c| c.is_ascii_uppercase()) => {
+            Err(DigestError::MalformedNonceCount(nc.clone()))
+        }
+        _ => Ok(()),
+    }
+}
+
+// Parses an `algorithm` parameter, matching the names of the built-in algorithms
+// case-insensitively unless `strict` is set.
+#[allow(deprecated)]
+fn parse_algorithm(value: &str, strict: bool) -> Result<HashAlgorithm, DigestError> {
+    const BUILT_IN_ALGORITHMS: [HashAlgorithm; 6] = [
+        HashAlgorithm::MD5,
+        HashAlgorithm::MD5Session,
+        HashAlgorithm::SHA256,
+        HashAlgorithm::SHA256Session,
+        HashAlgorithm::SHA512256,
+        HashAlgorithm::SHA512256Session,
+    ];
+
+    HashAlgorithm::from_str(value).or_else(|err| if strict {
+        Err(err)
+    } else {
+        BUILT_IN_ALGORITHMS
+            .iter()
+            .find(|algorithm| algorithm.to_string().eq_ignore_ascii_case(value))
+            .cloned()
+            .ok_or(err)
+    })
+}
+
+fn check_field_length(field: &'static str, len: usize, max: usize) -> Result<(), DigestError> {
+    if len > max {
+        Err(DigestError::FieldTooLong { field, len, max })
+    } else {
+        Ok(())
+    }
+}
+
+/// Parses a raw `Authorization` header value, including its case-insensitive `Digest` scheme.
+///
+/// This is intended for applications that do not use hyper's `Authorization<Digest>` header,
+/// which strips the scheme before parsing. `raw` can be either a string or the raw bytes received
+/// from the network, which must be valid UTF-8.
+pub fn parse_digest_header<R: AsRef<[u8]> + ?Sized>(raw: &R) -> Result<Digest, DigestError> {
+    let result = match ::std::str::from_utf8(raw.as_ref()) {
+        Ok(raw) => {
+            match strip_scheme(raw, "Digest") {
+                Some(parameters) => parse_digest_parameters(parameters, true),
+                None => Err(DigestError::MissingSchemePrefix),
+            }
+        }
+        Err(_) => Err(DigestError::InvalidUtf8),
+    };
+    if let Err(ref err) = result {
+        log_event!(debug, "Failed to parse Digest header: {:?}", err);
+    }
+    result
+}
+
+/// Logs the outcome of validating `digest`: successes at the debug level, and failures, with
+/// their reason, at the warn level.
+pub(crate) fn log_validation_result<E: fmt::Debug>(digest: &Digest, result: &Result<(), E>) {
+    match *result {
+        Ok(()) => {
+            log_event!(
+                debug,
+                "Digest auth validated for user {:?} realm {:?}",
+                digest.username,
+                digest.realm
+            )
+        }
+        Err(ref reason) => {
+            log_event!(
+                warn,
+                "Digest auth failed for user {:?} realm {:?}: {:?}",
+                digest.username,
+                digest.realm,
+                reason
+            )
+        }
+    }
+}
+
+impl From<Authorization<Digest>> for Digest {
+    fn from(header: Authorization<Digest>) -> Digest {
+        header.0
+    }
+}
+
+impl<'a> From<&'a Authorization<Digest>> for &'a Digest {
+    fn from(header: &'a Authorization<Digest>) -> &'a Digest {
+        &header.0
+    }
+}
+
+/// Parses the `Authorization` header of an `http::HeaderMap`, e.g. in frameworks which do not
+/// use hyper's typed headers.
+///
+/// Returns `DigestError::MissingAuthorizationHeader` if there is no `Authorization` header, and
+/// `DigestError::MissingSchemePrefix` if it does not use the `Digest` scheme. Otherwise, the
+/// error returned by `parse_digest_header` is passed through, e.g. `InvalidAlgorithm`.
+#[cfg(feature = "http")]
+impl<'a> TryFrom<&'a HeaderMap> for Digest {
+    type Error = DigestError;
+
+    fn try_from(headers: &'a HeaderMap) -> Result<Digest, DigestError> {
+        match headers.get(AUTHORIZATION) {
+            Some(value) => parse_digest_header(value.as_bytes()),
+            None => Err(DigestError::MissingAuthorizationHeader),
+        }
+    }
+}
+
+/// The example `Authorization` headers from
+/// [RFC 7616, section 3.9](https://tools.ietf.org/html/rfc7616#section-3.9).
+struct TestVector {
+    section: &'static str,
+    realm: &'static str,
+    nonce: &'static str,
+    username: &'static str,
+    algorithm: HashAlgorithm,
+    response: &'static str,
+}
+
+#[allow(deprecated)]
+const RFC7616_TEST_VECTORS: [TestVector; 4] = [
+    TestVector {
+        section: "RFC7616-3.9.1",
+        realm: "http-auth@example.org",
+        nonce: "7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v",
+        username: "Mufasa",
+        algorithm: HashAlgorithm::MD5,
+        response: "8ca523f5e9506fed4657c9700eebdbec",
+    },
+    TestVector {
+        section: "RFC7616-3.9.1",
+        realm: "http-auth@example.org",
+        nonce: "7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v",
+        username: "Mufasa",
+        algorithm: HashAlgorithm::SHA256,
+        response: "753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1",
+    },
+    // The example uses a userhash, but the RFC also describes the encoded username.
+    TestVector {
+        section: "RFC7616-3.9.2",
+        realm: "api@example.org",
+        nonce: "5TsQWLVdgBdmrQ0XsxbDODV+57QdFR34I9HAbC/RVvkK",
+        username: "488869477bf257147b804c45308cd62ac4e25eb717b12b298c79e62dcea254ec",
+        algorithm: HashAlgorithm::SHA512256,
+        response: "ae66e67d6b427bd3f120414a82e4acff38e8ecd9101d6c861229025f607a79dd",
+    },
+    TestVector {
+        section: "RFC7616-3.9.2",
+        realm: "api@example.org",
+        nonce: "5TsQWLVdgBdmrQ0XsxbDODV+57QdFR34I9HAbC/RVvkK",
+        username: "J\u{e4}s\u{f8}n Doe",
+        algorithm: HashAlgorithm::SHA512256,
+        response: "ae66e67d6b427bd3f120414a82e4acff38e8ecd9101d6c861229025f607a79dd",
+    },
+];
+
+/// Checks that a `response` value is a lowercase hexadecimal digest of the length generated by
+/// `algorithm`, as required by
+/// [RFC 7616, section 3.4](https://tools.ietf.org/html/rfc7616#section-3.4).
+pub fn validate_response_format(
+    response: &str,
+    algorithm: &HashAlgorithm,
+) -> Result<(), DigestError> {
+    let expected_len = algorithm.digest_hex_length();
+    if response.len() != expected_len {
+        return Err(DigestError::InvalidResponseFormat {
+            expected_len,
+            got_len: response.len(),
+        });
+    }
+    if !response.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+        return Err(DigestError::InvalidResponseCharacters);
+    }
+    Ok(())
+}
+
+/// Checks that a nonce contains no ASCII control characters or double quotes, which could be used
+/// for header injection if the nonce is reflected in a `WWW-Authenticate` header.
+///
+/// Servers can also use this to check nonces before issuing them.
+pub fn validate_nonce_characters(nonce: &str) -> Result<(), DigestError> {
+    if nonce.chars().any(|c| c.is_ascii_control() || c == '"') {
+        Err(DigestError::InvalidNonceCharacters)
+    } else {
+        Ok(())
+    }
+}
+
+/// Checks that a field contains no carriage returns or line feeds, which could be used for header
+/// injection if the field is reflected in another header.
+pub fn check_no_crlf(field_name: &'static str, value: &str) -> Result<(), DigestError> {
+    if value.contains(['\r', '\n']) {
+        Err(DigestError::CrlfInjection(field_name))
+    } else {
+        Ok(())
+    }
+}
+
+/// How `validate_request_uri_with_query_matching` compares the query strings of request URIs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueryMatching {
+    /// The query strings must be identical, including their absence.
+    Strict,
+    /// The query strings are ignored, e.g. for proxies which rewrite them.
+    Lenient,
+}
+
+/// Checks that the `uri` parameter of a `Digest` header refers to the same resource as the
+/// Request-URI of the request it was sent with, as required by
+/// [RFC 2617, section 3.2.2.5](https://tools.ietf.org/html/rfc2617#section-3.2.2.5).
+///
+/// This is `validate_request_uri_with_query_matching` with `QueryMatching::Strict`.
+pub fn validate_request_uri(digest_uri: &str, actual_uri: &str) -> Result<(), DigestError> {
+    validate_request_uri_with_query_matching(digest_uri, actual_uri, QueryMatching::Strict)
+}
+
+/// Checks that the `uri` parameter of a `Digest` header refers to the same resource as the
+/// Request-URI of the request it was sent with, comparing query strings as specified by
+/// `query_matching`.
+///
+/// Either URI may be absolute or a path; origins are only compared when both are absolute.
+/// Paths are compared after resolving dot segments and normalizing percent-encoding, so e.g.
+/// `/%7Emufasa` matches `/~mufasa`.
+pub fn validate_request_uri_with_query_matching(
+    digest_uri: &str,
+    actual_uri: &str,
+    query_matching: QueryMatching,
+) -> Result<(), DigestError> {
+    let mismatch = || {
+        DigestError::UriMismatch {
+            digest_uri: digest_uri.to_owned(),
+            actual_uri: actual_uri.to_owned(),
+        }
+    };
+    let (digest_origin, digest_url) = parse_request_uri(digest_uri).ok_or_else(mismatch)?;
+    let (actual_origin, actual_url) = parse_request_uri(actual_uri).ok_or_else(mismatch)?;
+    if let (Some(digest_origin), Some(actual_origin)) = (digest_origin, actual_origin) {
+        if digest_origin != actual_origin {
+            return Err(mismatch());
+        }
+    }
+    if normalize_percent_encoding(digest_url.path()) !=
+        normalize_percent_encoding(actual_url.path())
+    {
+        return Err(mismatch());
+    }
+    if query_matching == QueryMatching::Strict &&
+        digest_url.query().map(normalize_percent_encoding) !=
+            actual_url.query().map(normalize_percent_encoding)
+    {
+        return Err(mismatch());
+    }
+    Ok(())
+}
+
+// Parses a request URI, returning its origin if it is an absolute URI.
+fn parse_request_uri(uri: &str) -> Option<(Option<String>, Url)> {
+    if let Ok(url) = Url::parse(uri) {
+        return Some((Some(url.origin().ascii_serialization()), url));
+    }
+    let base = Url::parse("http://localhost/").expect("Base URL is valid");
+    base.join(uri).ok().map(|url| (None, url))
+}
+
+// RFC 3986, Section 6.2.2: decodes percent-encoded unreserved characters and uppercases the
+// hexadecimal digits of the remaining percent-encoded octets.
+fn normalize_percent_encoding(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut normalized = String::with_capacity(s.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = if bytes[i] == b'%' && i + 2 < bytes.len() &&
+            bytes[i + 1].is_ascii_hexdigit() && bytes[i + 2].is_ascii_hexdigit()
+        {
+            u8::from_str_radix(&s[i + 1..i + 3], 16).ok()
+        } else {
+            None
+        };
+        match escaped {
+            Some(b) if b.is_ascii_alphanumeric() || b"-._~".contains(&b) => {
+                normalized.push(b as char);
+                i += 3;
+            }
+            Some(b) => {
+                normalized.push_str(&format!("%{:02X}", b));
+                i += 3;
+            }
+            None => {
+                // URLs are serialized as ASCII, so every byte is a character.
+                normalized.push(bytes[i] as char);
+                i += 1;
+            }
+        }
+    }
+    normalized
+}
+
+/// Parameters whose values must be quoted strings, as defined in
+/// [RFC 7616, section 3.4](https://tools.ietf.org/html/rfc7616#section-3.4).
+const QUOTED_PARAMETERS: [&str; 7] =
+    ["username", "realm", "nonce", "uri", "response", "cnonce", "opaque"];
+/// Parameters whose values must be tokens.
+const TOKEN_PARAMETERS: [&str; 4] = ["algorithm", "qop", "nc", "userhash"];
+
+fn check_parameter_syntax(params: &[(&str, &str)]) -> Result<(), Error> {
+    for &(name, value) in params {
+        let is_named = |names: &[&str]| names.iter().any(|n| n.eq_ignore_ascii_case(name));
+        if is_quoted(value) {
+            if is_named(&TOKEN_PARAMETERS) {
+                return Err(Error::Header);
+            }
+        } else if is_named(&QUOTED_PARAMETERS) {
+            return Err(Error::Header);
+        }
+    }
+    Ok(())
+}
+
+fn parse_username(params: &[(&str, &str)]) -> Result<Username, Error> {
+    if let Some(value) = unraveled_param_value(params, "username") {
+        if unraveled_param_value(params, "username*").is_some() {
+            Err(Error::Header)
+        } else {
+            Ok(Username::Plain(value))
+        }
+    } else if let Some(encoded) = get_param(params, "username*") {
+        if let Some(userhash) = unraveled_param_value(params, "userhash") {
+            if userhash == "true" {
+                return Err(Error::Header);
+            }
+        }
+
+        // The extended value is percent-decoded by `parse_extended_value`, so it must not be
+        // unraveled first.
+        if let Ok(extended_value) = parse_extended_value(encoded) {
+            Ok(Username::Encoded(extended_value))
+        } else {
+            Err(Error::Header)
+        }
+    } else {
+        Err(Error::Header)
+    }
+}
+
+// RFC 7616, Section 3.3: a URI is in the protection space if it has a domain URI as a prefix.
+// Paths are compared like in `validate_request_uri_with_query_matching`, and only match at segment
+// boundaries, so that e.g. `/api` covers `/api/users` but neither `/apiary` nor `/api/../admin`.
+pub(crate) fn uri_in_domain(uri: &str, domain: &str) -> bool {
+    let (uri_origin, uri_url) = match parse_request_uri(uri) {
+        Some(parsed) => parsed,
+        None => return false,
+    };
+    let (domain_origin, domain_url) = match parse_request_uri(domain) {
+        Some(parsed) => parsed,
+        None => return false,
+    };
+    if let (Some(uri_origin), Some(domain_origin)) = (uri_origin, domain_origin) {
+        if uri_origin != domain_origin {
+            return false;
+        }
+    }
+
+    let uri_path = normalize_percent_encoding(uri_url.path());
+    let domain_path = normalize_percent_encoding(domain_url.path());
+    match uri_path.strip_prefix(&domain_path[..]) {
+        Some(rest) => rest.is_empty() || domain_path.ends_with('/') || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+impl FromStr for Digest {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Digest, Error> {
+        parse_and_log_digest_parameters(s, true).map_err(Error::from)
+    }
+}
+
+fn parse_and_log_digest_parameters(
+    s: &str,
+    strict_algorithm: bool,
+) -> Result<Digest, DigestError> {
+    let result = parse_digest_parameters(s, strict_algorithm);
+    if let Err(ref err) = result {
+        log_event!(debug, "Failed to parse Digest header: {:?}", err);
+    }
+    result
+}
+
+fn parse_digest_parameters(s: &str, strict_algorithm: bool) -> Result<Digest, DigestError> {
+    let param_map = parse_parameters(s).map_err(|_| DigestError::InvalidHeader)?;
+    check_parameter_syntax(&param_map).map_err(|_| DigestError::InvalidHeader)?;
+    let charset: Option<Charset>;
+    let userhash: bool;
+    let username = parse_username(&param_map).map_err(|_| DigestError::InvalidHeader)?;
+    if let Username::Plain(ref username) = username {
+        check_no_crlf("username", username)?;
+    }
+    let realm = match unraveled_param_value(&param_map, "realm") {
+        Some(value) => value,
+        None => return Err(DigestError::InvalidHeader),
+    };
+    check_no_crlf("realm", &realm)?;
+    let nonce = match unraveled_param_value(&param_map, "nonce") {
+        Some(value) => value,
+        None => return Err(DigestError::InvalidHeader),
+    };
+    check_no_crlf("nonce", &nonce)?;
+    validate_nonce_characters(&nonce)?;
+    let nonce_count = unraveled_param_value(&param_map, "nc")
+        .map(|value| parse_nonce_count(&value).map(NonceCount))
+        .transpose()?;
+    let response = match unraveled_param_value(&param_map, "response") {
+        Some(value) => value,
+        None => return Err(DigestError::InvalidHeader),
+    };
+    check_no_crlf("response", &response)?;
+    let request_uri = match unraveled_param_value(&param_map, "uri") {
+        Some(value) => value,
+        None => return Err(DigestError::InvalidHeader),
+    };
+    check_no_crlf("uri", &request_uri)?;
+    let algorithm = unraveled_param_value(&param_map, "algorithm")
+        .map(|value| parse_algorithm(&value, strict_algorithm))
+        .transpose()?
+        .unwrap_or_default();
+    validate_response_format(&response, &algorithm)?;
+    let qop = unraveled_param_value(&param_map, "qop")
+        .map(|value| Qop::from_str(&value).map_err(|_| DigestError::InvalidQop(value.clone())))
+        .transpose()?;
+    if let Some(value) = unraveled_param_value(&param_map, "charset") {
+        let utf8 = UniCase::new("utf-8".to_owned());
+        charset = if UniCase::new(value.clone()) == utf8 {
+            Some(Charset::Ext("UTF-8".to_owned()))
+        } else {
+            return Err(DigestError::InvalidHeader);
+        }
+    } else {
+        charset = None;
+    }
+    if let Some(value) = unraveled_param_value(&param_map, "userhash") {
+        match &value[..] {
+            "true" => userhash = true,
+            "false" => userhash = false,
+            _ => return Err(DigestError::InvalidHeader),
+        }
+    } else {
+        userhash = false;
+    }
+    let client_nonce = unraveled_param_value(&param_map, "cnonce");
+    if let Some(ref client_nonce) = client_nonce {
+        check_no_crlf("cnonce", client_nonce)?;
+    }
+    let opaque = unraveled_param_value(&param_map, "opaque");
+    if let Some(ref opaque) = opaque {
+        check_no_crlf("opaque", opaque)?;
+    }
+    Ok(Digest {
+        username,
+        realm,
+        nonce,
+        nonce_count,
+        response,
+        request_uri,
+        algorithm,
+        qop,
+        client_nonce,
+        opaque,
+        charset,
+        userhash,
+        method: None,
+    })
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Digest {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.serialized_parameters())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Digest {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Digest, D::Error> {
+        let parameters = String::deserialize(deserializer)?;
+        Digest::from_str(&parameters).map_err(DeError::custom)
+    }
+}
+
+impl Digest {
+    /// Parses the header parameters like `from_str`, after checking that neither the parameters
+    /// as a whole nor the fields limited by `config` are too long, and, if `config` is strict,
+    /// that the header does not deviate from the RFCs.
+    pub fn from_str_with_config(s: &str, config: &ParseConfig) -> Result<Digest, DigestError> {
+        check_field_length("header", s.len(), config.max_total_header_len)?;
+        if config.strict {
+            check_strict_syntax(s)?;
+        }
+        let digest = parse_and_log_digest_parameters(s, config.strict)?;
+        let username_len = match digest.username {
+            Username::Plain(ref username) => username.len(),
+            Username::Encoded(ref encoded) => encoded.value.len(),
+        };
+        check_field_length("username", username_len, config.max_username_len)?;
+        check_field_length("realm", digest.realm.len(), config.max_realm_len)?;
+        check_field_length("nonce", digest.nonce.len(), config.max_nonce_len)?;
+        if let Some(ref opaque) = digest.opaque {
+            check_field_length("opaque", opaque.len(), config.max_opaque_len)?;
+        }
+        Ok(digest)
+    }
+
+    /// A generous estimate of the length of the serialized parameters, so that they can usually be
+    /// serialized without reallocating.
+    fn serialized_length_estimate(&self) -> usize {
+        // `, ` + the longest parameter name + `=""`
+        const PARAMETER_OVERHEAD: usize = 16;
+        // `SHA-512-256-sess`
+        const MAX_ALGORITHM_LENGTH: usize = 16;
+        // Longer than any of the charset names used in practice, e.g. `UTF-8`
+        const CHARSET_LENGTH: usize = 16;
+
+        let username_length = match self.username {
+            Username::Plain(ref username) => username.len(),
+            // Every byte may be percent-encoded, plus the charset and language tag.
+            Username::Encoded(ref encoded) => encoded.value.len() * 3 + 32,
+        };
+        let optional_length = self.client_nonce.as_ref().map_or(0, String::len) +
+            self.opaque.as_ref().map_or(0, String::len) +
+            self.charset.as_ref().map_or(0, |_| CHARSET_LENGTH);
+
+        PARAMETER_OVERHEAD * 12 + username_length + self.realm.len() + self.nonce.len() +
+            self.response.len() + self.request_uri.len() + MAX_ALGORITHM_LENGTH +
+            "00000001".len() + "auth-int".len() + "true".len() + optional_length
+    }
+
+    // The header parameters, as serialized by `fmt_scheme`.
+    fn serialized_parameters(&self) -> String {
+        let mut serialized = String::with_capacity(self.serialized_length_estimate());
         match self.username {
             Username::Plain(ref username) => {
                 append_parameter(&mut serialized, "username", username, true)
@@ -104,8 +860,8 @@ impl Scheme for Digest {
         }
         append_parameter(&mut serialized, "realm", &self.realm, true);
         append_parameter(&mut serialized, "nonce", &self.nonce, true);
-        if let Some(ref nonce_count) = self.nonce_count {
-            append_parameter(&mut serialized, "nc", &nonce_count.to_string(), false);
+        if let Some(nonce_count) = self.nonce_count_hex() {
+            append_parameter(&mut serialized, "nc", &nonce_count, false);
         }
         append_parameter(&mut serialized, "response", &self.response, true);
         append_parameter(&mut serialized, "uri", &self.request_uri, true);
@@ -130,122 +886,177 @@ impl Scheme for Digest {
         if self.userhash {
             append_parameter(&mut serialized, "userhash", "true", false);
         }
-        write!(f, "{}", serialized)
+
+        serialized
     }
-}
 
-fn parse_username(map: &HashMap<UniCase<String>, String>) -> Result<Username, Error> {
-    if let Some(value) = unraveled_map_value(map, "username") {
-        if unraveled_map_value(map, "username*").is_some() {
-            Err(Error::Header)
-        } else {
-            Ok(Username::Plain(value))
-        }
-    } else if let Some(encoded) = unraveled_map_value(map, "username*") {
-        if let Some(userhash) = unraveled_map_value(map, "userhash") {
-            if userhash == "true" {
-                return Err(Error::Header);
-            }
-        }
+    /// The method of the HTTP request, if it has been set.
+    pub fn request_method_as_str(&self) -> Option<&str> {
+        self.method.as_deref()
+    }
 
-        if let Ok(extended_value) = parse_extended_value(&encoded) {
-            Ok(Username::Encoded(extended_value))
-        } else {
-            Err(Error::Header)
+    /// Returns a copy of the `Digest` with the method of the HTTP request set, so that a response
+    /// can be generated via `generate_digest_response_self`.
+    pub fn with_method(&self, method: &str) -> Digest {
+        Digest {
+            method: Some(method.to_owned()),
+            ..self.clone()
         }
-    } else {
-        Err(Error::Header)
     }
-}
 
-impl FromStr for Digest {
-    type Err = Error;
-    fn from_str(s: &str) -> Result<Digest, Error> {
-        let param_map = parse_parameters(s);
-        let username: Username;
-        let realm: String;
-        let nonce: String;
-        let response: String;
-        let request_uri: String;
-        let algorithm: HashAlgorithm;
-        let charset: Option<Charset>;
-        let userhash: bool;
-        match parse_username(&param_map) {
-            Ok(value) => username = value,
-            Err(err) => return Err(err),
-        }
-        match unraveled_map_value(&param_map, "realm") {
-            Some(value) => realm = value,
-            None => return Err(Error::Header),
-        }
-        match unraveled_map_value(&param_map, "nonce") {
-            Some(value) => nonce = value,
-            None => return Err(Error::Header),
-        }
-        let nonce_count = match NonceCount::from_parameters(&param_map) {
-            Ok(value) => value,
-            Err(err) => return Err(err),
-        };
-        match unraveled_map_value(&param_map, "response") {
-            Some(value) => response = value,
-            None => return Err(Error::Header),
-        }
-        match unraveled_map_value(&param_map, "uri") {
-            Some(value) => request_uri = value,
-            None => return Err(Error::Header),
-        }
-        if let Some(value) = unraveled_map_value(&param_map, "algorithm") {
-            match HashAlgorithm::from_str(&value[..]) {
-                Ok(converted) => algorithm = converted,
-                Err(_) => return Err(Error::Header),
-            }
-        } else {
-            algorithm = HashAlgorithm::MD5;
-        }
-        let qop = Qop::from_parameters(&param_map)?;
-        if let Some(value) = unraveled_map_value(&param_map, "charset") {
-            let utf8 = UniCase::new("utf-8".to_owned());
-            charset = if UniCase::new(value.clone()) == utf8 {
-                Some(Charset::Ext("UTF-8".to_owned()))
-            } else {
-                return Err(Error::Header);
-            }
-        } else {
-            charset = None;
+    /// The nonce count (parameter name `nc`) as an integer.
+    pub fn nonce_count(&self) -> Option<u32> {
+        self.nonce_count.as_ref().map(|nonce_count| nonce_count.0)
+    }
+
+    /// The nonce count (parameter name `nc`) as it appears in the header and the digest data
+    /// string, i.e. 8 lowercase hexadecimal digits.
+    pub fn nonce_count_hex(&self) -> Option<String> {
+        self.nonce_count.as_ref().map(|nonce_count| nonce_count.to_string())
+    }
+
+    /// Returns a copy of the `Digest` with the nonce count set from its hexadecimal
+    /// representation, as parsed by `parse_nonce_count`.
+    pub fn with_nonce_count_hex(&self, hex: &str) -> Result<Digest, DigestError> {
+        Ok(Digest {
+            nonce_count: Some(NonceCount(parse_nonce_count(hex)?)),
+            ..self.clone()
+        })
+    }
+
+    /// Whether `algorithm` is based on a SHA-2 hash function. Useful for servers that require
+    /// clients to use stronger algorithms than MD5.
+    pub fn algorithm_is_sha2(&self) -> bool {
+        self.algorithm.is_sha2_family()
+    }
+
+    /// Whether `algorithm` provides at least `minimum_bits` bits of security, as defined by
+    /// `HashAlgorithm::security_bits`. Servers can use this with
+    /// `RECOMMENDED_MINIMUM_SECURITY_BITS` to reject MD5-based `Digest`s.
+    pub fn meets_minimum_security(&self, minimum_bits: u32) -> bool {
+        self.algorithm.security_bits() >= minimum_bits
+    }
+
+    /// Whether `response` is plausibly a digest generated by `algorithm`, i.e. whether it is a
+    /// lowercase hexadecimal string of the right length, as checked by `validate_response_format`
+    /// when parsing.
+    ///
+    /// This is a cheap check that servers can use to reject malformed headers before doing a full
+    /// validation.
+    pub fn is_valid_hex_response(&self) -> bool {
+        validate_response_format(&self.response, &self.algorithm).is_ok()
+    }
+
+    /// Whether this `Digest` has an empty, plain user name (and is not using a userhash), which
+    /// some servers accept as an anonymous user with limited privileges.
+    pub fn is_anonymous(&self) -> bool {
+        self.username == Username::Plain(String::new()) && !self.userhash
+    }
+
+    /// Checks that the parameters required by `qop` and `algorithm` are present: `nc` and `cnonce`
+    /// when `qop` is specified, and `cnonce` when a session algorithm is used.
+    ///
+    /// Headers are parsed without this check, so that RFC 2069 compatibility is preserved, and a
+    /// missing parameter otherwise only causes response generation to fail. Servers can use this
+    /// to reject such headers up front.
+    pub fn validate_qop_consistency(&self) -> Result<(), DigestError> {
+        if self.qop.is_some() && self.nonce_count.is_none() {
+            return Err(DigestError::MissingNonceCount);
         }
-        if let Some(value) = unraveled_map_value(&param_map, "userhash") {
-            match &value[..] {
-                "true" => userhash = true,
-                "false" => userhash = false,
-                _ => return Err(Error::Header),
-            }
-        } else {
-            userhash = false;
+        if (self.qop.is_some() || self.algorithm.is_session()) && self.client_nonce.is_none() {
+            return Err(DigestError::MissingClientNonce);
         }
-        Ok(Digest {
-            username: username,
-            realm: realm,
-            nonce: nonce,
-            nonce_count: nonce_count,
-            response: response,
-            request_uri: request_uri,
-            algorithm: algorithm,
-            qop: qop,
-            client_nonce: unraveled_map_value(&param_map, "cnonce"),
-            opaque: unraveled_map_value(&param_map, "opaque"),
-            charset: charset,
-            userhash: userhash,
+        Ok(())
+    }
+
+    /// Whether `realm` contains characters that are problematic in a quoted string: backslashes,
+    /// double quotes, control characters, or non-ASCII characters.
+    ///
+    /// Servers can use this to warn about realm values that clients may not handle consistently.
+    /// See [RFC 7617, section 2.2](https://tools.ietf.org/html/rfc7617#section-2.2).
+    pub fn realm_contains_sensitive_characters(&self) -> bool {
+        self.realm.chars().any(|c| {
+            c == '\\' || c == '"' || c.is_control() || !c.is_ascii()
         })
     }
-}
 
-impl Digest {
+    /// The RFC section identifier (e.g. `RFC7616-3.9.1`) of the
+    /// [RFC 7616 example](https://tools.ietf.org/html/rfc7616#section-3.9) whose realm, nonce,
+    /// username, algorithm, and response match this `Digest`, if any.
+    ///
+    /// This is a convenience for checking an implementation against known-good values.
+    pub fn fields_match_rfc7616_test_vector(&self) -> Option<&'static str> {
+        RFC7616_TEST_VECTORS
+            .iter()
+            .find(|vector| {
+                vector.realm == self.realm && vector.nonce == self.nonce &&
+                    vector.username.as_bytes() == self.username.as_bytes() &&
+                    vector.algorithm == self.algorithm &&
+                    vector.response == self.response
+            })
+            .map(|vector| vector.section)
+    }
+
+    /// Serializes the `Digest` to a JSON string containing its header parameters.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Could not serialize digest to JSON")
+    }
+
+    /// Deserializes a `Digest` from a JSON string created by `to_json`.
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> Result<Digest, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Serializes the `Digest` as the value of an `Authorization` header for hyper 1.x, including
+    /// the `Digest` scheme.
+    ///
+    /// Returns `DigestError::InvalidHeader` if a parameter contains control characters, which are
+    /// not allowed in header values.
+    #[cfg(feature = "hyper1")]
+    pub fn to_header_value(&self) -> Result<HeaderValue, DigestError> {
+        HeaderValue::from_str(&self.to_string()).map_err(|_| DigestError::InvalidHeader)
+    }
+
+    /// Parses the value of a hyper 1.x `Authorization` header, including its `Digest` scheme.
+    #[cfg(feature = "hyper1")]
+    pub fn from_header_value(value: &HeaderValue) -> Result<Digest, DigestError> {
+        parse_digest_header(value.as_bytes())
+    }
+
+    /// Serializes this `Digest` as the value of an `http` crate `Authorization` header, including
+    /// the `Digest` scheme.
+    ///
+    /// Returns `DigestError::InvalidHeader` if a parameter contains control characters, which are
+    /// not allowed in header values.
+    #[cfg(feature = "http")]
+    pub fn to_authorization_header_value(&self) -> Result<HttpHeaderValue, DigestError> {
+        HttpHeaderValue::from_str(&self.to_string()).map_err(|_| DigestError::InvalidHeader)
+    }
+
+    /// Determines whether a client may preemptively send credentials for `target_uri`, given the
+    /// `challenge` that this `Digest` was generated for.
+    ///
+    /// The realm and algorithm must match the challenge, and `target_uri` must be in the
+    /// challenge's protection space, as defined in
+    /// [RFC 7616, section 3.3](https://tools.ietf.org/html/rfc7616#section-3.3).
+    pub fn can_preemptively_authenticate(&self, target_uri: &str, challenge: &Challenge) -> bool {
+        if self.realm != challenge.realm || self.algorithm != challenge.algorithm {
+            return false;
+        }
+
+        challenge.covers_uri(target_uri)
+    }
+
     /// Generates a userhash, as defined in
     /// [RFC 7616, section 3.4.4](https://tools.ietf.org/html/rfc7616#section-3.4.4).
-    pub fn userhash(algorithm: &HashAlgorithm, username: Vec<u8>, realm: String) -> String {
-        let mut to_hash = username.clone();
+    pub fn userhash(algorithm: &HashAlgorithm, username: &Username, realm: &str) -> String {
+        let username = username.as_bytes();
+        let mut to_hash = Vec::with_capacity(username.len() + realm.len() + 1);
+        to_hash.extend_from_slice(username);
         to_hash.push(b':');
-        to_hash.append(&mut realm.into_bytes());
+        to_hash.extend_from_slice(realm.as_bytes());
         algorithm.hex_digest(to_hash.as_slice())
     }
 
@@ -253,33 +1064,21 @@ impl Digest {
     /// [RFC 7616, section 3.4.4](https://tools.ietf.org/html/rfc7616#section-3.4.4)), given a
     /// `Digest` header.
     ///
-    /// If userhash is `false`, returns `false`.
-    pub fn validate_userhash(&self, username: Username) -> bool {
+    /// Returns `Ok(false)` without comparing anything if userhash is `false`, and an error if the
+    /// username is encoded, since an encoded username cannot be a userhash.
+    pub fn validate_userhash(&self, username: &Username) -> Result<bool, DigestError> {
+        if !self.userhash {
+            return Ok(false);
+        }
         match self.username {
             Username::Plain(ref userhash) => {
-                let name = match username {
-                    Username::Plain(value) => value.into_bytes(),
-                    Username::Encoded(encoded) => encoded.value,
-                };
-                *userhash == Digest::userhash(&self.algorithm, name, self.realm.clone())
+                let expected = Digest::userhash(&self.algorithm, username, &self.realm);
+                Ok(*userhash == expected)
             }
-            Username::Encoded(_) => false,
+            Username::Encoded(_) => Err(DigestError::EncodedUsernameWithUserhash),
         }
     }
 
-    fn simple_a1(username: Username, realm: String, password: String) -> Vec<u8> {
-        let mut a1: Vec<u8> = match username {
-            Username::Plain(name) => name.clone().into_bytes(),
-            Username::Encoded(encoded) => encoded.value.clone(),
-        };
-        a1.push(b':');
-        a1.append(&mut realm.into_bytes());
-        a1.push(b':');
-        a1.append(&mut password.into_bytes());
-
-        a1
-    }
-
     /// Generates a simple hexadecimal digest from an A1 value and given algorithm.
     ///
     /// This is intended to be used in applications that use the `htdigest` style of secret hash
@@ -290,93 +1089,176 @@ impl Digest {
     /// This is the definition when the algorithm is "unspecified".
     pub fn simple_hashed_a1(
         algorithm: &HashAlgorithm,
+        username: &str,
+        realm: &str,
+        password: &str,
+    ) -> String {
+        generate_simple_hashed_a1(algorithm, username.as_bytes(), realm, password)
+    }
+
+    /// Generates an A1 value, as defined in
+    /// [RFC 7616, section 3.4.2](https://tools.ietf.org/html/rfc7616#section-3.4.2) (and
+    /// [RFC 2617, section 3.2.2.2](https://tools.ietf.org/html/rfc2617#section-3.2.2.2)).
+    ///
+    /// Returns an error if the algorithm is a session variant and `cnonce` is missing. The A1 value
+    /// contains the plaintext password, so both are zeroed when dropped.
+    pub fn a1(
+        &self,
         username: Username,
-        realm: String,
         password: String,
-    ) -> String {
-        algorithm.hex_digest(Digest::simple_a1(username, realm, password).as_slice())
+    ) -> Result<Zeroizing<Vec<u8>>, DigestError> {
+        let password = Zeroizing::new(password);
+        self.a1_using_bytes(username.as_bytes(), &password)
+    }
+
+    /// Generates the A1 value of the non-session algorithms, i.e. `username:realm:password`. The
+    /// password is prepared via SASLprep if the `saslprep` feature is enabled, and if `charset` is
+    /// `UTF-8`, the username and password are normalized to NFC.
+    pub(crate) fn simple_a1_using_bytes(
+        &self,
+        username: &[u8],
+        password: &str,
+    ) -> Result<Zeroizing<Vec<u8>>, DigestError> {
+        let password = prepare_password(password)?;
+        let is_utf8 = match self.charset {
+            Some(Charset::Ext(ref charset)) => charset.eq_ignore_ascii_case("UTF-8"),
+            _ => false,
+        };
+        if !is_utf8 {
+            return Ok(generate_simple_a1(username, &self.realm, &password));
+        }
+        let password = Zeroizing::new(normalize_for_a1(&password));
+        Ok(match ::std::str::from_utf8(username) {
+            Ok(username) => {
+                let username = normalize_for_a1(username);
+                generate_simple_a1(username.as_bytes(), &self.realm, &password)
+            }
+            Err(_) => generate_simple_a1(username, &self.realm, &password),
+        })
     }
 
-    // RFC 7616, Section 3.4.2
-    fn a1(&self, username: Username, password: String) -> Result<Vec<u8>, Error> {
-        let realm = self.realm.clone();
+    #[allow(deprecated)]
+    fn a1_using_bytes(
+        &self,
+        username: &[u8],
+        password: &str,
+    ) -> Result<Zeroizing<Vec<u8>>, DigestError> {
+        let simple_a1 = self.simple_a1_using_bytes(username, password)?;
         match self.algorithm {
             HashAlgorithm::MD5 |
             HashAlgorithm::SHA256 |
-            HashAlgorithm::SHA512256 => Ok(Digest::simple_a1(username, realm, password)),
+            HashAlgorithm::SHA512256 |
+            HashAlgorithm::Custom(_) => Ok(simple_a1),
 
             HashAlgorithm::MD5Session |
             HashAlgorithm::SHA256Session |
             HashAlgorithm::SHA512256Session => {
                 if let Some(ref client_nonce) = self.client_nonce {
-                    let simple_hashed_a1 =
-                        self.algorithm.hex_digest(
-                            Digest::simple_a1(username, realm, password)
-                                .as_slice(),
-                        );
-                    let mut a1 = simple_hashed_a1.into_bytes();
+                    let simple_hashed_a1 = self.algorithm.hex_digest(simple_a1.as_slice());
+                    let mut a1 = Zeroizing::new(simple_hashed_a1.into_bytes());
+                    a1.reserve(self.nonce.len() + client_nonce.len() + 2);
                     a1.push(b':');
-                    a1.append(&mut self.nonce.clone().into_bytes());
+                    a1.extend_from_slice(self.nonce.as_bytes());
                     a1.push(b':');
-                    a1.append(&mut client_nonce.clone().into_bytes());
+                    a1.extend_from_slice(client_nonce.as_bytes());
                     Ok(a1)
                 } else {
-                    Err(Error::Header)
+                    Err(DigestError::MissingClientNonce)
                 }
             }
         }
     }
 
-    /// Generates a hexadecimal digest from an A1 value.
+    /// Generates a hexadecimal digest from an A1 value, i.e. `H(A1)`.
     ///
     /// To see how an A1 value is constructed, see
     /// [RFC 7616, section 3.4.2](https://tools.ietf.org/html/rfc7616#section-3.4.2).
-    fn hashed_a1(&self, username: Username, password: String) -> Result<String, Error> {
-        if let Ok(a1) = self.a1(username, password) {
-            Ok(self.algorithm.hex_digest(a1.as_slice()))
-        } else {
-            Err(Error::Header)
-        }
+    pub fn hashed_a1(&self, username: Username, password: String) -> Result<String, DigestError> {
+        let password = Zeroizing::new(password);
+        self.hashed_a1_using_bytes(username.as_bytes(), &password)
+    }
+
+    fn hashed_a1_using_bytes(
+        &self,
+        username: &[u8],
+        password: &str,
+    ) -> Result<String, DigestError> {
+        let a1 = self.a1_using_bytes(username, password)?;
+        Ok(self.algorithm.hex_digest(a1.as_slice()))
     }
 
-    // RFC 7616, Section 3.4.3
-    fn a2(&self, method: Method, entity_body: &[u8]) -> String {
+    /// Generates an A2 value, as defined in
+    /// [RFC 7616, section 3.4.3](https://tools.ietf.org/html/rfc7616#section-3.4.3) (and
+    /// [RFC 2617, section 3.2.2.3](https://tools.ietf.org/html/rfc2617#section-3.2.2.3)).
+    ///
+    /// `entity_body` is only used when `qop` is `auth-int`.
+    pub fn a2(&self, method: &str, entity_body: &[u8]) -> String {
         match self.qop {
             Some(Qop::AuthInt) => {
-                format!(
-                    "{}:{}:{}",
-                    method,
-                    self.request_uri,
-                    self.algorithm.hex_digest(entity_body)
-                )
+                self.a2_using_body_hash(method, &self.algorithm.hex_digest(entity_body))
             }
             _ => format!("{}:{}", method, self.request_uri),
         }
     }
 
-    fn hashed_a2(&self, method: Method, entity_body: &[u8]) -> String {
+    fn a2_using_body_hash(&self, method: &str, body_hash: &str) -> String {
+        match self.qop {
+            Some(Qop::AuthInt) => format!("{}:{}:{}", method, self.request_uri, body_hash),
+            _ => format!("{}:{}", method, self.request_uri),
+        }
+    }
+
+    /// Generates a hexadecimal digest from an A2 value, i.e. `H(A2)`.
+    ///
+    /// To see how an A2 value is constructed, see
+    /// [RFC 7616, section 3.4.3](https://tools.ietf.org/html/rfc7616#section-3.4.3).
+    pub fn hashed_a2(&self, method: &str, entity_body: &[u8]) -> String {
         self.algorithm.hex_digest(
             self.a2(method, entity_body).as_bytes(),
         )
     }
 
-    fn kd(algorithm: &HashAlgorithm, secret: String, data: String) -> String {
-        let value = format!("{}:{}", secret, data);
-        algorithm.hex_digest(value.as_bytes())
+    /// Generates the hexadecimal "keyed digest" `KD(secret, data)`, which is
+    /// `H(concat(secret, ":", data))`, as defined in
+    /// [RFC 7616, section 3.4.1](https://tools.ietf.org/html/rfc7616#section-3.4.1) (and
+    /// [RFC 2617, section 3.2.1](https://tools.ietf.org/html/rfc2617#section-3.2.1)).
+    pub fn kd(algorithm: &HashAlgorithm, secret: String, data: String) -> String {
+        Digest::keyed_digest(algorithm, &secret, &data)
+    }
+
+    /// Generates the raw (i.e., not hexadecimal) keyed digest `KD(secret, data)`, e.g. for use as
+    /// binary key material.
+    ///
+    /// Returns `DigestError::InvalidAlgorithm` if a custom algorithm's hasher did not generate a
+    /// hexadecimal digest.
+    pub fn kd_raw(
+        algorithm: &HashAlgorithm,
+        secret: &[u8],
+        data: &[u8],
+    ) -> Result<Vec<u8>, DigestError> {
+        let mut value = Vec::with_capacity(secret.len() + data.len() + 1);
+        value.extend_from_slice(secret);
+        value.push(b':');
+        value.extend_from_slice(data);
+        algorithm
+            .digest(value.as_slice())
+            .ok_or_else(|| DigestError::InvalidAlgorithm(algorithm.to_string()))
+    }
+
+    fn keyed_digest(algorithm: &HashAlgorithm, secret: &str, data: &str) -> String {
+        generate_kd(algorithm, secret, data)
     }
 
     fn using_username_and_password(
         &self,
-        method: Method,
+        method: &str,
         entity_body: &[u8],
-        username: Username,
-        password: String,
-    ) -> Result<String, Error> {
-        if let Ok(a1) = self.hashed_a1(username, password) {
-            self.using_hashed_a1(method, entity_body, a1)
-        } else {
-            Err(Error::Header)
-        }
+        username: &[u8],
+        password: &str,
+    ) -> Result<String, DigestError> {
+        let a1 = self.hashed_a1_using_bytes(username, password)?;
+        let a2 = self.hashed_a2(method, entity_body);
+        self.using_hashed_a1_and_a2(&a1, &a2, self.nonce_count.as_ref())
     }
 
     /// Generates a digest, given an HTTP request and a password.
@@ -385,15 +1267,46 @@ impl Digest {
     /// [RFC 2616, secion 7.2](https://tools.ietf.org/html/rfc2616#section-7.2).
     pub fn using_password(
         &self,
-        method: Method,
+        method: &str,
         entity_body: &[u8],
         password: String,
-    ) -> Result<String, Error> {
-        if let Ok(a1) = self.hashed_a1(self.username.clone(), password) {
-            self.using_hashed_a1(method, entity_body, a1)
-        } else {
-            Err(Error::Header)
-        }
+    ) -> Result<String, DigestError> {
+        let password = Zeroizing::new(password);
+        self.using_username_and_password(method, entity_body, self.username.as_bytes(), &password)
+    }
+
+    /// Generates a digest, given a password and the entity body of an HTTP request whose method is
+    /// stored in `method`.
+    ///
+    /// `entity_body` is defined in
+    /// [RFC 2616, secion 7.2](https://tools.ietf.org/html/rfc2616#section-7.2).
+    pub fn generate_digest_response_self(
+        &self,
+        password: &str,
+        entity_body: &[u8],
+    ) -> Result<String, DigestError> {
+        let method = match self.request_method_as_str() {
+            Some(method) if !method.is_empty() => method,
+            _ => return Err(DigestError::MissingMethod),
+        };
+        self.using_username_and_password(method, entity_body, self.username.as_bytes(), password)
+    }
+
+    /// Generates a digest, given an HTTP request, a password, and a nonce count to use instead of
+    /// `nonce_count`, without modifying the `Digest`.
+    ///
+    /// This is intended for tests and for replaying recorded exchanges.
+    pub fn using_password_with_nonce_count(
+        &self,
+        method: &str,
+        entity_body: &[u8],
+        password: String,
+        nonce_count: u32,
+    ) -> Result<String, DigestError> {
+        let password = Zeroizing::new(password);
+        let a1 = self.hashed_a1_using_bytes(self.username.as_bytes(), &password)?;
+        let a2 = self.hashed_a2(method, entity_body);
+        self.using_hashed_a1_and_a2(&a1, &a2, Some(&NonceCount(nonce_count)))
     }
 
     /// Generates a digest, given an HTTP request and a hexadecimal digest of an A1 string.
@@ -405,47 +1318,97 @@ impl Digest {
     /// generation.
     pub fn using_hashed_a1(
         &self,
-        method: Method,
+        method: &str,
         entity_body: &[u8],
         a1: String,
-    ) -> Result<String, Error> {
+    ) -> Result<String, DigestError> {
         let a2 = self.hashed_a2(method, entity_body);
+        self.using_hashed_a1_and_a2(&a1, &a2, self.nonce_count.as_ref())
+    }
+
+    /// Generates a digest, given an HTTP request, a hexadecimal digest of the entity body, and a
+    /// hexadecimal digest of an A1 string.
+    ///
+    /// `body_hash` is only used when `qop` is `auth-int`, and can be computed incrementally with
+    /// `DigestBodyHasher`, so that large entity bodies do not need to be held in memory.
+    pub fn using_hashed_a1_and_body_hash(
+        &self,
+        method: &str,
+        body_hash: &str,
+        a1: String,
+    ) -> Result<String, DigestError> {
+        let a1 = Zeroizing::new(a1);
+        let a2 = self.algorithm.hex_digest(
+            self.a2_using_body_hash(method, body_hash).as_bytes(),
+        );
+        self.using_hashed_a1_and_a2(&a1, &a2, self.nonce_count.as_ref())
+    }
+
+    fn using_hashed_a1_and_a2(
+        &self,
+        a1: &str,
+        a2: &str,
+        nonce_count: Option<&NonceCount>,
+    ) -> Result<String, DigestError> {
+        let data = self.kd_data(a2, nonce_count)?;
+        Ok(Digest::keyed_digest(&self.algorithm, a1, &data))
+    }
+
+    fn kd_data(&self, a2: &str, nonce_count: Option<&NonceCount>) -> Result<String, DigestError> {
         let data: String;
         if let Some(ref qop) = self.qop {
             match *qop {
                 Qop::Auth | Qop::AuthInt => {
-                    if self.client_nonce.is_none() || self.nonce_count.is_none() {
-                        return Err(Error::Header);
-                    }
-                    let nonce = self.nonce.clone();
-                    let nonce_count = self.nonce_count.clone().expect("No nonce count found");
-                    let client_nonce = self.client_nonce.clone().expect("No client nonce found");
-                    data = format!("{}:{}:{}:{}:{}", nonce, nonce_count, client_nonce, qop, a2);
+                    let nonce_count = match nonce_count {
+                        Some(nonce_count) => nonce_count,
+                        None => return Err(DigestError::MissingNonceCount),
+                    };
+                    let client_nonce = match self.client_nonce {
+                        Some(ref client_nonce) => client_nonce,
+                        None => return Err(DigestError::MissingClientNonce),
+                    };
+                    data = format!(
+                        "{}:{}:{}:{}:{}",
+                        self.nonce,
+                        nonce_count,
+                        client_nonce,
+                        qop,
+                        a2
+                    );
                 }
             }
         } else {
             data = format!("{}:{}", self.nonce, a2);
         }
-        Ok(Digest::kd(&self.algorithm, a1, data))
+        Ok(data)
     }
 
     fn validate_using_username_and_password(
         &self,
-        method: Method,
+        method: &str,
         entity_body: &[u8],
-        username: Username,
-        password: String,
-    ) -> bool {
-        if let Ok(hex_digest) = self.using_username_and_password(
-            method,
-            entity_body,
-            username,
-            password,
-        )
-        {
-            hex_digest == self.response
+        username: &[u8],
+        password: &str,
+    ) -> Result<(), ValidationFailure> {
+        let result = self.validate_response_with(|| {
+            self.using_username_and_password(method, entity_body, username, password)
+                .map_err(ValidationFailure::MalformedDigest)
+        });
+        log_validation_result(self, &result);
+        result
+    }
+
+    // Shared by all of the ways to validate a `Digest`, including the `validator` module, so that
+    // the structure of the `Digest` is always checked before `expected` computes the response.
+    pub(crate) fn validate_response_with<F>(&self, expected: F) -> Result<(), ValidationFailure>
+    where
+        F: FnOnce() -> Result<String, ValidationFailure>,
+    {
+        self.validate_qop_consistency().map_err(ValidationFailure::StructureInvalid)?;
+        if expected()? == self.response {
+            Ok(())
         } else {
-            false
+            Err(ValidationFailure::ResponseMismatch)
         }
     }
 
@@ -455,34 +1418,111 @@ impl Digest {
     /// [RFC 2616, secion 7.2](https://tools.ietf.org/html/rfc2616#section-7.2).
     pub fn validate_using_password(
         &self,
-        method: Method,
+        method: &str,
         entity_body: &[u8],
         password: String,
-    ) -> bool {
+    ) -> Result<(), ValidationFailure> {
+        let password = Zeroizing::new(password);
         self.validate_using_username_and_password(
             method,
             entity_body,
-            self.username.clone(),
-            password,
+            self.username.as_bytes(),
+            &password,
         )
     }
 
+    /// Validates a `Digest.response`, given the method and entity body of an HTTP request and a
+    /// password. Returns `false` if `method` is empty.
+    ///
+    /// `entity_body` is defined in
+    /// [RFC 2616, secion 7.2](https://tools.ietf.org/html/rfc2616#section-7.2).
+    pub fn response_matches_password(
+        &self,
+        method: &str,
+        entity_body: &[u8],
+        password: &str,
+    ) -> bool {
+        !method.is_empty() &&
+            self.validate_using_username_and_password(
+                method,
+                entity_body,
+                self.username.as_bytes(),
+                password,
+            ).is_valid()
+    }
+
+    /// Validates a `Digest.response`, given the method and entity body of an HTTP request and a
+    /// hexadecimal digest of an A1 string. Returns `false` if `method` is empty.
+    ///
+    /// `entity_body` is defined in
+    /// [RFC 2616, secion 7.2](https://tools.ietf.org/html/rfc2616#section-7.2).
+    pub fn response_matches_ha1(&self, method: &str, entity_body: &[u8], ha1: &str) -> bool {
+        !method.is_empty() &&
+            self.validate_using_hashed_a1(method, entity_body, ha1.to_owned())
+                .is_valid()
+    }
+
+    /// Validates a `Digest.response`, given an HTTP request and a password, returning the
+    /// intermediate values of the computation if the response does not match.
+    ///
+    /// Returns `Ok(None)` if the response is valid, and an error if a response cannot be
+    /// generated from the `Digest` parameters at all. Only available with the `debug-validation`
+    /// feature, as the intermediate values are derived from the password.
+    #[cfg(feature = "debug-validation")]
+    pub fn validate_digest_verbose(
+        &self,
+        method: &str,
+        entity_body: &[u8],
+        password: String,
+    ) -> Result<Option<DigestResponseMismatchError>, DigestError> {
+        let password = Zeroizing::new(password);
+        let a1 = self.hashed_a1_using_bytes(self.username.as_bytes(), &password)?;
+        let a2 = self.hashed_a2(method, entity_body);
+        let data = self.kd_data(&a2, self.nonce_count.as_ref())?;
+        let expected_response = Digest::keyed_digest(&self.algorithm, &a1, &data);
+        if expected_response == self.response {
+            return Ok(None);
+        }
+        Ok(Some(DigestResponseMismatchError {
+            expected_kd_input: format!("{}:{}", a1, data),
+            expected_a1_hash: a1,
+            expected_a2_hash: a2,
+            expected_response,
+            received_response: self.response.clone(),
+        }))
+    }
+
     /// Validates a `Digest.username` and `Digest.response`, given an HTTP request, a username,
-    /// and a password. If a userhash is specified, that is validated first.
+    /// and a password. If a userhash is specified, that is validated first, and a mismatch is
+    /// reported as `ValidationFailure::ResponseMismatch`.
     ///
     /// `entity_body` is defined in
     /// [RFC 2616, secion 7.2](https://tools.ietf.org/html/rfc2616#section-7.2).
     pub fn validate_using_userhash_and_password(
         &self,
-        method: Method,
+        method: &str,
         entity_body: &[u8],
         username: Username,
         password: String,
-    ) -> bool {
-        if self.userhash && !self.validate_userhash(username.clone()) {
-            return false;
+    ) -> Result<(), ValidationFailure> {
+        let password = Zeroizing::new(password);
+        if self.userhash {
+            let result = match self.validate_userhash(&username) {
+                Ok(true) => Ok(()),
+                Ok(false) => Err(ValidationFailure::ResponseMismatch),
+                Err(error) => Err(ValidationFailure::StructureInvalid(error)),
+            };
+            if result.is_err() {
+                log_validation_result(self, &result);
+                return result;
+            }
         }
-        self.validate_using_username_and_password(method, entity_body, username, password)
+        self.validate_using_username_and_password(
+            method,
+            entity_body,
+            username.as_bytes(),
+            &password,
+        )
     }
 
     /// Validates a `Digest.response`, given an HTTP request and a hexadecimal digest of an
@@ -493,11 +1533,120 @@ impl Digest {
     ///
     /// This is intended to be used in applications that use the `htdigest` style of secret hash
     /// generation.
-    pub fn validate_using_hashed_a1(&self, method: Method, entity_body: &[u8], a1: String) -> bool {
-        if let Ok(hex_digest) = self.using_hashed_a1(method, entity_body, a1) {
-            hex_digest == self.response
-        } else {
-            false
-        }
+    pub fn validate_using_hashed_a1(
+        &self,
+        method: &str,
+        entity_body: &[u8],
+        a1: String,
+    ) -> Result<(), ValidationFailure> {
+        let result = self.validate_response_with(|| {
+            self.using_hashed_a1(method, entity_body, a1)
+                .map_err(ValidationFailure::MalformedDigest)
+        });
+        log_validation_result(self, &result);
+        result
+    }
+}
+
+/// Builds a modified copy of a `Digest`, e.g. so that server middleware can normalize a parsed
+/// header before serializing it again.
+#[derive(Clone, Debug)]
+pub struct DigestBuilder {
+    digest: Digest,
+}
+
+impl DigestBuilder {
+    /// Creates a builder whose fields are copied from an existing `Digest`.
+    pub fn from_digest(digest: &Digest) -> DigestBuilder {
+        DigestBuilder { digest: digest.clone() }
+    }
+
+    /// Creates a builder from the parameters of an `Authorization` header value (i.e., without
+    /// the `Digest` scheme).
+    pub fn from_authorization_str(s: &str) -> Result<DigestBuilder, DigestError> {
+        Ok(DigestBuilder { digest: parse_and_log_digest_parameters(s, true)? })
+    }
+
+    /// Sets the username or userhash.
+    pub fn username(mut self, username: Username) -> DigestBuilder {
+        self.digest.username = username;
+        self
+    }
+
+    /// Sets the authentication realm.
+    pub fn realm(mut self, realm: String) -> DigestBuilder {
+        self.digest.realm = realm;
+        self
+    }
+
+    /// Sets the cryptographic nonce.
+    pub fn nonce(mut self, nonce: String) -> DigestBuilder {
+        self.digest.nonce = nonce;
+        self
+    }
+
+    /// Sets or clears the nonce count.
+    pub fn nonce_count(mut self, nonce_count: Option<NonceCount>) -> DigestBuilder {
+        self.digest.nonce_count = nonce_count;
+        self
+    }
+
+    /// Sets the hexadecimal digest.
+    pub fn response(mut self, response: String) -> DigestBuilder {
+        self.digest.response = response;
+        self
+    }
+
+    /// Sets the request URI.
+    pub fn request_uri(mut self, request_uri: String) -> DigestBuilder {
+        self.digest.request_uri = request_uri;
+        self
+    }
+
+    /// Sets the hash algorithm.
+    pub fn algorithm(mut self, algorithm: HashAlgorithm) -> DigestBuilder {
+        self.digest.algorithm = algorithm;
+        self
+    }
+
+    /// Sets or clears the quality of protection.
+    pub fn qop(mut self, qop: Option<Qop>) -> DigestBuilder {
+        self.digest.qop = qop;
+        self
+    }
+
+    /// Sets or clears the client nonce.
+    pub fn client_nonce(mut self, client_nonce: Option<String>) -> DigestBuilder {
+        self.digest.client_nonce = client_nonce;
+        self
+    }
+
+    /// Sets or clears the opaque string.
+    pub fn opaque(mut self, opaque: Option<String>) -> DigestBuilder {
+        self.digest.opaque = opaque;
+        self
+    }
+
+    /// Sets or clears the character set.
+    pub fn charset(mut self, charset: Option<Charset>) -> DigestBuilder {
+        self.digest.charset = charset;
+        self
+    }
+
+    /// Sets whether the username is a userhash.
+    pub fn userhash(mut self, userhash: bool) -> DigestBuilder {
+        self.digest.userhash = userhash;
+        self
+    }
+
+    /// Sets or clears the method of the HTTP request.
+    pub fn method(mut self, method: Option<String>) -> DigestBuilder {
+        self.digest.method = method;
+        self
+    }
+
+    /// Returns the built `Digest`.
+    pub fn build(self) -> Digest {
+        self.digest
     }
 }
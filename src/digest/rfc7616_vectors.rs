@@ -0,0 +1,185 @@
+// Copyright (c) 2017 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! The examples of [RFC 7616, section 3.9](https://tools.ietf.org/html/rfc7616#section-3.9), one
+//! test per example: parsing, response generation, validation, and userhash generation.
+
+#![cfg(test)]
+
+use super::{Digest, RFC7616_TEST_VECTORS, TestVector, Username, parse_digest_header};
+use super::test_helper::{rfc7616_digest_header, rfc7616_sha512_256_header, rfc7616_username};
+use super::super::types::HashAlgorithm;
+
+// See: RFC 7616, Section 3.9.1
+const SECTION_3_9_1_PASSWORD: &str = "Circle of Life";
+
+// See: RFC 7616, Section 3.9.2
+const SECTION_3_9_2_PASSWORD: &str = "Secret, or not?";
+
+fn test_vector(section: &str, username: &str) -> &'static TestVector {
+    RFC7616_TEST_VECTORS
+        .iter()
+        .find(|vector| vector.section == section && vector.username == username)
+        .expect("Unknown test vector")
+}
+
+// The username of the example in section 3.9.2, and the example with its userhash.
+fn section_3_9_2_vectors() -> (&'static TestVector, &'static TestVector) {
+    let encoded = test_vector("RFC7616-3.9.2", "J\u{e4}s\u{f8}n Doe");
+    let userhash = RFC7616_TEST_VECTORS
+        .iter()
+        .find(|vector| vector.section == "RFC7616-3.9.2" && vector.username != encoded.username)
+        .expect("Missing userhash test vector");
+    (encoded, userhash)
+}
+
+fn section_3_9_1_header(vector: &TestVector) -> String {
+    format!(
+        "Digest username=\"{}\", realm=\"{}\", uri=\"/dir/index.html\", algorithm={}, \
+         nonce=\"{}\", nc=00000001, cnonce=\"f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ\", \
+         qop=auth, response=\"{}\", opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\"",
+        vector.username,
+        vector.realm,
+        vector.algorithm,
+        vector.nonce,
+        vector.response
+    )
+}
+
+fn section_3_9_2_header(vector: &TestVector, username: &str) -> String {
+    format!(
+        "Digest {}, realm=\"{}\", uri=\"/doe.json\", algorithm={}, nonce=\"{}\", nc=00000001, \
+         cnonce=\"NTg6RKcb9boFIAS3KrFK9BGeh+iDa/sm6jUMp2wds69v\", qop=auth, \
+         response=\"{}\", opaque=\"HRPCssKJSGjCrkzDg8OhwpzCiGPChXYjwrI2QmXDnsOS\"",
+        username,
+        vector.realm,
+        vector.algorithm,
+        vector.nonce,
+        vector.response
+    )
+}
+
+// The example `Authorization` header does not repeat the charset of the challenge.
+fn section_3_9_2_digest(username: Username, userhash: bool) -> Digest {
+    let mut digest = rfc7616_sha512_256_header(String::new(), userhash);
+    digest.username = username;
+    digest.charset = None;
+    digest
+}
+
+fn assert_section_3_9_1_example(algorithm: HashAlgorithm) {
+    let vector = RFC7616_TEST_VECTORS
+        .iter()
+        .find(|vector| vector.section == "RFC7616-3.9.1" && vector.algorithm == algorithm)
+        .expect("Unknown test vector");
+    let response = vector.response;
+    let digest = parse_digest_header(&section_3_9_1_header(vector)).unwrap();
+    assert_eq!(rfc7616_digest_header(algorithm, response), digest);
+    assert_eq!(Some("RFC7616-3.9.1"), digest.fields_match_rfc7616_test_vector());
+    assert_eq!(
+        Ok(response.to_owned()),
+        digest.using_password("GET", b"", SECTION_3_9_1_PASSWORD.to_owned())
+    );
+    assert_eq!(
+        Ok(()),
+        digest.validate_using_password("GET", b"", SECTION_3_9_1_PASSWORD.to_owned())
+    );
+    // The example does not use a userhash, so check the userhash that a client would send.
+    assert!(!digest.userhash);
+    assert_eq!(
+        digest.algorithm.hex_digest(b"Mufasa:http-auth@example.org"),
+        Digest::userhash(&digest.algorithm, &digest.username, &digest.realm)
+    );
+}
+
+#[allow(deprecated)]
+#[test]
+fn test_section_3_9_1_md5() {
+    assert_section_3_9_1_example(HashAlgorithm::MD5);
+}
+
+#[test]
+fn test_section_3_9_1_sha256() {
+    assert_section_3_9_1_example(HashAlgorithm::SHA256);
+}
+
+#[test]
+fn test_section_3_9_2_sha512_256_with_userhash() {
+    let (encoded, vector) = section_3_9_2_vectors();
+    let username = Username::Plain(encoded.username.to_owned());
+    let header = section_3_9_2_header(vector, &format!("username=\"{}\"", vector.username));
+    let digest = parse_digest_header(&format!("{}, userhash=true", header)).unwrap();
+    assert_eq!(
+        section_3_9_2_digest(Username::Plain(vector.username.to_owned()), true),
+        digest
+    );
+    assert_eq!(Some("RFC7616-3.9.2"), digest.fields_match_rfc7616_test_vector());
+
+    assert_eq!(
+        vector.username,
+        Digest::userhash(&digest.algorithm, &username, &digest.realm)
+    );
+    assert_eq!(Ok(true), digest.validate_userhash(&username));
+
+    // The response is generated from the username, not the userhash.
+    let mut unhashed = digest.clone();
+    unhashed.username = username.clone();
+    unhashed.userhash = false;
+    assert_eq!(
+        Ok(vector.response.to_owned()),
+        unhashed.using_password("GET", b"", SECTION_3_9_2_PASSWORD.to_owned())
+    );
+    assert_eq!(
+        Ok(()),
+        digest.validate_using_userhash_and_password(
+            "GET",
+            b"",
+            username,
+            SECTION_3_9_2_PASSWORD.to_owned(),
+        )
+    );
+}
+
+#[test]
+fn test_section_3_9_2_sha512_256_with_encoded_username() {
+    let (vector, userhash) = section_3_9_2_vectors();
+    let header = section_3_9_2_header(vector, "username*=UTF-8''J%C3%A4s%C3%B8n%20Doe");
+    let digest = parse_digest_header(&header).unwrap();
+    assert_eq!(section_3_9_2_digest(rfc7616_username(), false), digest);
+    assert_eq!(Some("RFC7616-3.9.2"), digest.fields_match_rfc7616_test_vector());
+    match digest.username {
+        Username::Encoded(_) => {}
+        Username::Plain(_) => panic!("Expected an encoded username"),
+    }
+    assert_eq!(Ok(vector.username.to_owned()), digest.username.to_display_string());
+
+    assert_eq!(
+        Ok(vector.response.to_owned()),
+        digest.using_password("GET", b"", SECTION_3_9_2_PASSWORD.to_owned())
+    );
+    assert_eq!(
+        Ok(()),
+        digest.validate_using_password("GET", b"", SECTION_3_9_2_PASSWORD.to_owned())
+    );
+    assert_eq!(
+        userhash.username,
+        Digest::userhash(&digest.algorithm, &digest.username, &digest.realm)
+    );
+}
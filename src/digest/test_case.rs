@@ -0,0 +1,87 @@
+// Copyright (c) 2017 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Deterministic generation of valid `Digest` headers, e.g. for seeding fuzzers.
+
+use super::{Digest, Username};
+use super::super::types::{HashAlgorithm, NonceCount, Qop};
+
+const ALPHANUMERIC: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+const HEXADECIMAL: &[u8] = b"0123456789abcdef";
+
+/// A SplitMix64 generator, which is small and good enough to derive test parameters from a seed.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn string(&mut self, alphabet: &[u8], length: usize) -> String {
+        (0..length)
+            .map(|_| alphabet[(self.next() % alphabet.len() as u64) as usize] as char)
+            .collect()
+    }
+}
+
+/// Generates a valid `Digest` and the password that its `response` was generated from.
+///
+/// All of the other parameters (username, realm, nonce, nonce count, client nonce, and request
+/// URI) are derived from `seed`, so the same arguments always generate the same test case. The
+/// `method` is set to `GET`, and the response is generated for an empty entity body.
+pub fn generate_digest_test_case(
+    algorithm: &HashAlgorithm,
+    qop: Option<Qop>,
+    seed: u64,
+) -> (Digest, String) {
+    let mut rng = SplitMix64(seed);
+    let username = rng.string(ALPHANUMERIC, 8);
+    let realm = format!("{}@example.org", rng.string(ALPHANUMERIC, 8));
+    let nonce = rng.string(ALPHANUMERIC, 32);
+    let nonce_count = NonceCount((rng.next() % 0xffff) as u32 + 1);
+    let client_nonce = rng.string(HEXADECIMAL, 16);
+    let request_uri = format!("/{}", rng.string(ALPHANUMERIC, 12));
+    let password = rng.string(ALPHANUMERIC, 16);
+
+    let has_client_nonce = qop.is_some() || algorithm.is_session();
+    let mut digest = Digest {
+        username: Username::Plain(username),
+        realm,
+        nonce,
+        nonce_count: qop.as_ref().map(|_| nonce_count),
+        response: String::new(),
+        request_uri,
+        algorithm: algorithm.clone(),
+        qop,
+        client_nonce: if has_client_nonce { Some(client_nonce) } else { None },
+        opaque: None,
+        charset: None,
+        userhash: false,
+        method: Some("GET".to_owned()),
+    };
+    digest.response = digest
+        .using_password("GET", b"", password.clone())
+        .expect("Generated Digest parameters are incomplete");
+    (digest, password)
+}
@@ -18,12 +18,17 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
 // THE SOFTWARE.
 
-#![allow(dead_code)]
+#![allow(dead_code, missing_docs)]
 
-use hyper::header::{Authorization, Header, Headers, Raw};
+use hyper::header::{Authorization, Charset, Header, Headers, Raw};
 use hyper::header::parsing::parse_extended_value;
-use parsing::test_helper;
+#[cfg(feature = "logging")]
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use proptest::prelude::*;
+#[cfg(feature = "logging")]
+use std::sync::{Mutex, Once};
 use super::{Digest, Username};
+use super::super::parsing::test_helper;
 use super::super::types::{HashAlgorithm, NonceCount, Qop};
 
 pub fn assert_parsed_header_equal(expected: Authorization<Digest>, data: &str) {
@@ -40,7 +45,7 @@ pub fn assert_serialized_header_equal(digest: Digest, actual: &str) {
     assert_eq!(headers.to_string(), format!("{}\r\n", actual))
 }
 
-pub fn parse_digest_header(data: &str) -> Authorization<Digest> {
+pub fn parse_authorization_header(data: &str) -> Authorization<Digest> {
     Header::parse_header(&Raw::from(data)).expect("Could not parse digest header")
 }
 
@@ -48,6 +53,7 @@ pub fn rfc2069_username() -> Username {
     Username::Plain("Mufasa".to_owned())
 }
 
+#[allow(deprecated)]
 fn rfc2069_digest_header(realm: &str) -> Digest {
     Digest {
         username: rfc2069_username(),
@@ -65,6 +71,7 @@ fn rfc2069_digest_header(realm: &str) -> Digest {
         opaque: None,
         charset: None,
         userhash: false,
+        method: None,
     }
 }
 
@@ -84,12 +91,13 @@ pub fn rfc2617_digest_header(algorithm: HashAlgorithm) -> Digest {
         nonce_count: Some(NonceCount(1)),
         response: "6629fae49393a05397450978507c4ef1".to_owned(),
         request_uri: "/dir/index.html".to_owned(),
-        algorithm: algorithm,
+        algorithm,
         qop: Some(Qop::Auth),
         client_nonce: Some("0a4f113b".to_owned()),
         opaque: Some("5ccc069c403ebaf9f0171e9517f40e41".to_owned()),
         charset: None,
         userhash: false,
+        method: None,
     }
 }
 
@@ -107,12 +115,13 @@ pub fn rfc7616_digest_header(algorithm: HashAlgorithm, response: &str) -> Digest
         nonce_count: Some(NonceCount(1)),
         response: response.to_owned(),
         request_uri: "/dir/index.html".to_owned(),
-        algorithm: algorithm,
+        algorithm,
         qop: Some(Qop::Auth),
         client_nonce: Some("f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ".to_owned()),
         opaque: Some("FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS".to_owned()),
         charset: None,
         userhash: false,
+        method: None,
     }
 }
 
@@ -131,6 +140,132 @@ pub fn rfc7616_sha512_256_header(username: String, userhash: bool) -> Digest {
         client_nonce: Some("NTg6RKcb9boFIAS3KrFK9BGeh+iDa/sm6jUMp2wds69v".to_owned()),
         opaque: Some("HRPCssKJSGjCrkzDg8OhwpzCiGPChXYjwrI2QmXDnsOS".to_owned()),
         charset: Some(Charset::Ext("UTF-8".to_owned())),
-        userhash: userhash,
+        userhash,
+        method: None,
     }
 }
+
+// Quoted parameter values, excluding the characters that would need to be escaped or
+// percent-decoded.
+const QUOTED_VALUE: &str = "[A-Za-z0-9._~/@+=:;-][A-Za-z0-9 ._~/@+=:;-]{0,31}";
+
+fn arbitrary_username() -> impl Strategy<Value = Username> {
+    prop_oneof![
+        QUOTED_VALUE.prop_map(Username::Plain),
+        any::<String>().prop_map(|value| {
+            let mut encoded =
+                parse_extended_value("UTF-8''").expect("Could not parse extended value");
+            encoded.value = value.into_bytes();
+            Username::Encoded(encoded)
+        }),
+    ]
+}
+
+#[allow(deprecated)]
+fn arbitrary_algorithm() -> impl Strategy<Value = HashAlgorithm> {
+    prop_oneof![
+        Just(HashAlgorithm::MD5),
+        Just(HashAlgorithm::MD5Session),
+        Just(HashAlgorithm::SHA256),
+        Just(HashAlgorithm::SHA256Session),
+        Just(HashAlgorithm::SHA512256),
+        Just(HashAlgorithm::SHA512256Session),
+    ]
+}
+
+fn arbitrary_response(algorithm: &HashAlgorithm) -> impl Strategy<Value = String> {
+    let pattern = format!("[0-9a-f]{{{}}}", algorithm.digest_hex_length());
+    prop::string::string_regex(&pattern).expect("Could not compile response pattern")
+}
+
+/// Generates `Digest`s that can be serialized, parsed, and used to generate a response: `nc` and
+/// `cnonce` are set when `qop` is, `cnonce` is set for session algorithms, and `userhash` is only
+/// set for plain usernames.
+pub fn arbitrary_digest() -> impl Strategy<Value = Digest> {
+    (arbitrary_algorithm(), arbitrary_username())
+        .prop_flat_map(|(algorithm, username)| {
+            let response = arbitrary_response(&algorithm);
+            let userhash = match username {
+                Username::Plain(_) => any::<bool>().boxed(),
+                Username::Encoded(_) => Just(false).boxed(),
+            };
+            (
+                Just(algorithm),
+                Just(username),
+                (QUOTED_VALUE, QUOTED_VALUE, QUOTED_VALUE, response),
+                (
+                    prop::option::of(prop_oneof![Just(Qop::Auth), Just(Qop::AuthInt)]),
+                    1..u32::MAX,
+                    QUOTED_VALUE,
+                    prop::option::of(QUOTED_VALUE),
+                    any::<bool>(),
+                    userhash,
+                ),
+            )
+        })
+        .prop_map(|(algorithm,
+                    username,
+                    (realm, nonce, request_uri, response),
+                    (qop, nonce_count, client_nonce, opaque, utf8, userhash))| {
+            let has_client_nonce = qop.is_some() || algorithm.is_session();
+            Digest {
+                username,
+                realm,
+                nonce,
+                nonce_count: qop.as_ref().map(|_| NonceCount(nonce_count)),
+                response,
+                request_uri,
+                algorithm,
+                qop,
+                client_nonce: if has_client_nonce { Some(client_nonce) } else { None },
+                opaque,
+                charset: if utf8 { Some(Charset::Ext("UTF-8".to_owned())) } else { None },
+                userhash,
+                method: None,
+            }
+        })
+}
+
+/// Records every log message, so that tests can check that authentication events are logged.
+#[cfg(feature = "logging")]
+struct CapturingLogger {
+    messages: Mutex<Vec<(Level, String)>>,
+}
+
+#[cfg(feature = "logging")]
+impl Log for CapturingLogger {
+    fn enabled(&self, _: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        let mut messages = self.messages.lock().expect("Log messages lock poisoned");
+        messages.push((record.level(), record.args().to_string()));
+    }
+
+    fn flush(&self) {}
+}
+
+#[cfg(feature = "logging")]
+static LOGGER: CapturingLogger = CapturingLogger { messages: Mutex::new(Vec::new()) };
+
+/// Installs a logger which records every message, if it has not been installed already.
+#[cfg(feature = "logging")]
+pub fn capture_log_messages() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        log::set_logger(&LOGGER).expect("Could not install test logger");
+        log::set_max_level(LevelFilter::Debug);
+    });
+}
+
+/// Returns the messages logged so far (by any test) which contain `needle`.
+#[cfg(feature = "logging")]
+pub fn logged_messages_containing(needle: &str) -> Vec<(Level, String)> {
+    let messages = LOGGER.messages.lock().expect("Log messages lock poisoned");
+    messages
+        .iter()
+        .filter(|&(_, message)| message.contains(needle))
+        .cloned()
+        .collect()
+}
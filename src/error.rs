@@ -0,0 +1,305 @@
+// Copyright (c) 2017 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Errors that can occur when generating or validating digests.
+
+use hyper::Error as HyperError;
+use std::error::Error;
+use std::fmt;
+
+/// The reasons why a `Digest` header cannot be parsed, or why a digest cannot be generated from a
+/// set of `Digest` parameters.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DigestError {
+    /// The `cnonce` parameter is required by the `algorithm` or `qop`, but is missing.
+    MissingClientNonce,
+    /// The `nc` parameter is required by the `qop`, but is missing.
+    MissingNonceCount,
+    /// The `username*` parameter was used together with `userhash=true`, which is not allowed by
+    /// [RFC 7616, section 3.4.4](https://tools.ietf.org/html/rfc7616#section-3.4.4).
+    EncodedUsernameWithUserhash,
+    /// A response to a challenge was requested to be incremented before one was generated.
+    NoPreviousResponse,
+    /// The header value does not start with the `Digest` authentication scheme.
+    MissingSchemePrefix,
+    /// The header parameters are missing required fields or contain invalid values.
+    InvalidHeader,
+    /// A response was requested to be generated from a `Digest` without a valid request method.
+    MissingMethod,
+    /// The `response` parameter does not have the length of a digest generated by the `algorithm`.
+    InvalidResponseFormat {
+        /// The length of a hexadecimal digest generated by the `algorithm`.
+        expected_len: usize,
+        /// The length of the `response` parameter.
+        got_len: usize,
+    },
+    /// The `response` parameter contains characters other than lowercase hexadecimal digits.
+    InvalidResponseCharacters,
+    /// The `algorithm` parameter is not one of the algorithms defined by
+    /// [RFC 7616, section 3.3](https://tools.ietf.org/html/rfc7616#section-3.3).
+    InvalidAlgorithm(String),
+    /// The `nonce` parameter contains ASCII control characters or double quotes.
+    InvalidNonceCharacters,
+    /// The header value is not valid UTF-8.
+    InvalidUtf8,
+    /// A `qop` value is neither `auth` nor `auth-int`.
+    InvalidQop(String),
+    /// The `nc` parameter is not exactly 8 hexadecimal digits.
+    MalformedNonceCount(String),
+    /// A challenge cannot be answered with the previously used credentials, because it is not
+    /// stale, or because its realm or algorithm differs from the previous challenge.
+    CredentialsRequired,
+    /// A header field is longer than allowed by a `ParseConfig`.
+    FieldTooLong {
+        /// The name of the field, or `header` for the header as a whole.
+        field: &'static str,
+        /// The length of the field.
+        len: usize,
+        /// The maximum length of the field.
+        max: usize,
+    },
+    /// The password contains characters which are prohibited by SASLprep. Only returned when the
+    /// `saslprep` feature is enabled.
+    InvalidPassword,
+    /// The operating system's random number generator could not be used.
+    RandomUnavailable,
+    /// The `uri` parameter does not match the Request-URI of the request.
+    UriMismatch {
+        /// The `uri` parameter of the `Digest` header.
+        digest_uri: String,
+        /// The Request-URI of the request.
+        actual_uri: String,
+    },
+    /// A field contains a carriage return or line feed, which could be used for header injection.
+    CrlfInjection(&'static str),
+    /// A challenge was built with an empty nonce.
+    EmptyNonce,
+    /// The request has no `Authorization` header.
+    MissingAuthorizationHeader,
+    /// The `opaque` parameter was not returned unchanged from the challenge.
+    OpaqueMismatch {
+        /// The `opaque` parameter of the challenge.
+        expected: Option<String>,
+        /// The `opaque` parameter of the `Digest` header.
+        got: Option<String>,
+    },
+    /// The Request-URI of the `Digest` header is outside the protection space of the challenge.
+    UriOutsideDomain(String),
+    /// A nonce is not valid base64.
+    InvalidNonceEncoding,
+    /// The decoded bytes of an RFC 5987-encoded username are not valid UTF-8.
+    InvalidUtf8InUsername,
+}
+
+impl fmt::Display for DigestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DigestError::MissingClientNonce => write!(f, "Missing required field: cnonce"),
+            DigestError::MissingNonceCount => write!(f, "Missing required field: nc"),
+            DigestError::EncodedUsernameWithUserhash => {
+                write!(f, "Encoded username cannot be used with userhash")
+            }
+            DigestError::NoPreviousResponse => write!(f, "No response has been generated yet"),
+            DigestError::MissingSchemePrefix => write!(f, "Missing authentication scheme: Digest"),
+            DigestError::InvalidHeader => write!(f, "Invalid Digest header parameters"),
+            DigestError::MissingMethod => write!(f, "Missing request method"),
+            DigestError::InvalidResponseFormat {
+                expected_len,
+                got_len,
+            } => {
+                write!(
+                    f,
+                    "Invalid response length: expected {}, got {}",
+                    expected_len,
+                    got_len
+                )
+            }
+            DigestError::InvalidResponseCharacters => {
+                write!(f, "Invalid response: expected lowercase hexadecimal digits")
+            }
+            DigestError::InvalidAlgorithm(ref algorithm) => {
+                write!(f, "Invalid algorithm string: '{}'", algorithm)
+            }
+            DigestError::InvalidNonceCharacters => {
+                write!(f, "Invalid nonce: contains control characters or double quotes")
+            }
+            DigestError::InvalidUtf8 => write!(f, "Invalid header: not valid UTF-8"),
+            DigestError::InvalidQop(ref qop) => write!(f, "Invalid qop value: '{}'", qop),
+            DigestError::MalformedNonceCount(ref nc) => {
+                write!(f, "Malformed nonce count: expected 8 hexadecimal digits, got '{}'", nc)
+            }
+            DigestError::CredentialsRequired => {
+                write!(f, "Challenge requires credentials to be entered again")
+            }
+            DigestError::FieldTooLong { field, len, max } => {
+                write!(f, "Field too long: {} is {} bytes, maximum is {}", field, len, max)
+            }
+            DigestError::InvalidPassword => {
+                write!(f, "Invalid password: contains characters prohibited by SASLprep")
+            }
+            DigestError::RandomUnavailable => write!(f, "Random number generator unavailable"),
+            DigestError::UriMismatch { ref digest_uri, ref actual_uri } => {
+                write!(f, "URI mismatch: {} does not match {}", digest_uri, actual_uri)
+            }
+            DigestError::CrlfInjection(field) => {
+                write!(f, "Field contains a carriage return or line feed: {}", field)
+            }
+            DigestError::EmptyNonce => write!(f, "Nonce is empty"),
+            DigestError::MissingAuthorizationHeader => write!(f, "Missing Authorization header"),
+            DigestError::OpaqueMismatch { ref expected, ref got } => {
+                write!(f, "Opaque mismatch: expected {:?}, got {:?}", expected, got)
+            }
+            DigestError::UriOutsideDomain(ref uri) => {
+                write!(f, "URI is outside the protection space: {}", uri)
+            }
+            DigestError::InvalidNonceEncoding => write!(f, "Nonce is not valid base64"),
+            DigestError::InvalidUtf8InUsername => write!(f, "Username is not valid UTF-8"),
+        }
+    }
+}
+
+impl Error for DigestError {}
+
+/// The reasons why a `Digest` fails validation against a set of credentials.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ValidationFailure {
+    /// A response could not be generated from the `Digest` parameters and the credentials, e.g.
+    /// because the password is rejected by SASLprep.
+    MalformedDigest(DigestError),
+    /// The `response` (or the userhash) does not match the one generated from the credentials.
+    ResponseMismatch,
+    /// The `Digest` parameters are inconsistent with each other or with the request, e.g. `cnonce`
+    /// is missing when `qop` is specified, or `uri` does not match the request URI.
+    StructureInvalid(DigestError),
+    /// The `Digest` was generated for a different realm than the one being validated.
+    RealmMismatch,
+    /// No credentials could be found for the username and realm.
+    UnknownUsername,
+    /// The `nonce` was not issued by the server.
+    UnknownNonce,
+    /// The `nc` has already been used with the `nonce`, i.e. the request is a replay.
+    NonceCountReused,
+    /// The `nc` is not the one following the highest `nc` used with the `nonce` so far.
+    NonceCountOutOfOrder {
+        /// The next nonce count.
+        expected: u32,
+        /// The nonce count of the `Digest`.
+        got: u32,
+    },
+    /// The `Digest` is anonymous (see `Digest::is_anonymous`), but there are no credentials for
+    /// the empty username, i.e. anonymous access is not allowed.
+    AnonymousNotAllowed,
+    /// The `nonce` was issued by the server, but has since expired, so the client should retry
+    /// with a `stale` challenge's nonce.
+    NonceExpired,
+}
+
+impl fmt::Display for ValidationFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ValidationFailure::MalformedDigest(ref error) => {
+                write!(f, "Cannot generate digest: {}", error)
+            }
+            ValidationFailure::ResponseMismatch => write!(f, "Digest response does not match"),
+            ValidationFailure::StructureInvalid(ref error) => {
+                write!(f, "Invalid Digest parameters: {}", error)
+            }
+            ValidationFailure::RealmMismatch => write!(f, "Digest realm does not match"),
+            ValidationFailure::UnknownUsername => write!(f, "Digest username is unknown"),
+            ValidationFailure::UnknownNonce => write!(f, "Digest nonce is unknown"),
+            ValidationFailure::NonceCountReused => write!(f, "Digest nonce count was already used"),
+            ValidationFailure::NonceCountOutOfOrder { expected, got } => {
+                write!(
+                    f,
+                    "Digest nonce count is out of order: expected {:08x}, got {:08x}",
+                    expected,
+                    got
+                )
+            }
+            ValidationFailure::AnonymousNotAllowed => write!(f, "Anonymous access is not allowed"),
+            ValidationFailure::NonceExpired => write!(f, "Digest nonce has expired"),
+        }
+    }
+}
+
+impl Error for ValidationFailure {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            ValidationFailure::MalformedDigest(ref error) |
+            ValidationFailure::StructureInvalid(ref error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Convenience methods for the results of validating a `Digest`.
+pub trait ValidationOutcome {
+    /// Whether the `Digest` passed validation, for callers that do not need the failure reason.
+    fn is_valid(&self) -> bool;
+}
+
+impl<E> ValidationOutcome for Result<(), E> {
+    fn is_valid(&self) -> bool {
+        self.is_ok()
+    }
+}
+
+/// The intermediate values used to compute the expected response to a challenge, for debugging
+/// why a received `Digest.response` did not match.
+///
+/// These values are derived from the password, so they should never be logged in production.
+#[cfg(feature = "debug-validation")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct DigestResponseMismatchError {
+    /// The hexadecimal digest of A1, i.e. `H(A1)`.
+    pub expected_a1_hash: String,
+    /// The hexadecimal digest of A2, i.e. `H(A2)`.
+    pub expected_a2_hash: String,
+    /// The value hashed by `KD`, i.e. `concat(H(A1), ":", data)`.
+    pub expected_kd_input: String,
+    /// The expected hexadecimal response.
+    pub expected_response: String,
+    /// The `response` parameter sent by the client.
+    pub received_response: String,
+}
+
+#[cfg(feature = "debug-validation")]
+impl fmt::Display for DigestResponseMismatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Digest response mismatch: expected {}, received {} (H(A1)={}, H(A2)={}, KD input={})",
+            self.expected_response,
+            self.received_response,
+            self.expected_a1_hash,
+            self.expected_a2_hash,
+            self.expected_kd_input
+        )
+    }
+}
+
+#[cfg(feature = "debug-validation")]
+impl Error for DigestResponseMismatchError {}
+
+impl From<DigestError> for HyperError {
+    fn from(_: DigestError) -> HyperError {
+        HyperError::Header
+    }
+}
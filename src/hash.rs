@@ -0,0 +1,178 @@
+// Copyright (c) 2017 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Cryptographic hash function backends.
+//!
+//! By default, hashes are computed via `crypto-hash`, which uses the platform's native crypto
+//! library. Alternatively, one of the following features can be enabled (but not both):
+//!
+//! * `ring-backend`: SHA-256 and SHA-512 are computed via `ring`, and MD5 (which `ring` does not
+//!   support) via `md-5`.
+//! * `pure-rust-backend`: SHA-256 and SHA-512 are computed via `sha2`, and MD5 via `md-5`.
+//!
+//! All backends produce identical output. Only the `ring-backend` and `pure-rust-backend` features
+//! can be used without the `std` feature.
+
+use alloc::vec::Vec;
+
+#[cfg(not(any(feature = "crypto-hash", feature = "ring-backend", feature = "pure-rust-backend")))]
+compile_error!(
+    "One of the `crypto-hash`, `ring-backend`, or `pure-rust-backend` features must be enabled"
+);
+
+#[cfg(all(feature = "ring-backend", feature = "pure-rust-backend"))]
+compile_error!("The `ring-backend` and `pure-rust-backend` features cannot be enabled together");
+
+/// The hash functions that the HTTP digest algorithms are built on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Function {
+    MD5,
+    SHA256,
+    SHA512,
+}
+
+/// An incremental hasher for a `Function`.
+pub trait HashBackend: Sized {
+    /// Creates a hasher for the given function.
+    fn new(function: Function) -> Self;
+    /// Adds `data` to the hash.
+    fn update(&mut self, data: &[u8]);
+    /// Consumes the hasher and returns the raw digest.
+    fn finish(self) -> Vec<u8>;
+}
+
+#[cfg(all(
+    feature = "crypto-hash",
+    not(any(feature = "ring-backend", feature = "pure-rust-backend"))
+))]
+pub use self::crypto_hash_backend::CryptoHashBackend as Hasher;
+#[cfg(feature = "pure-rust-backend")]
+pub use self::pure_rust_backend::PureRustBackend as Hasher;
+#[cfg(feature = "ring-backend")]
+pub use self::ring_backend::RingBackend as Hasher;
+
+#[cfg(all(
+    feature = "crypto-hash",
+    not(any(feature = "ring-backend", feature = "pure-rust-backend"))
+))]
+mod crypto_hash_backend {
+    use std::io::Write;
+    use super::{Function, HashBackend};
+
+    pub struct CryptoHashBackend(crypto_hash::Hasher);
+
+    impl HashBackend for CryptoHashBackend {
+        fn new(function: Function) -> CryptoHashBackend {
+            let algorithm = match function {
+                Function::MD5 => crypto_hash::Algorithm::MD5,
+                Function::SHA256 => crypto_hash::Algorithm::SHA256,
+                Function::SHA512 => crypto_hash::Algorithm::SHA512,
+            };
+            CryptoHashBackend(crypto_hash::Hasher::new(algorithm))
+        }
+
+        fn update(&mut self, data: &[u8]) {
+            self.0.write_all(data).expect(
+                "writing to a hasher should not fail",
+            );
+        }
+
+        fn finish(mut self) -> Vec<u8> {
+            self.0.finish()
+        }
+    }
+}
+
+#[cfg(feature = "ring-backend")]
+mod ring_backend {
+    use alloc::vec::Vec;
+    use md5::{Digest, Md5};
+    use ring::digest::{Context, SHA256, SHA512};
+    use super::{Function, HashBackend};
+
+    pub enum RingBackend {
+        MD5(Md5),
+        Ring(Context),
+    }
+
+    impl HashBackend for RingBackend {
+        fn new(function: Function) -> RingBackend {
+            match function {
+                Function::MD5 => RingBackend::MD5(Md5::new()),
+                Function::SHA256 => RingBackend::Ring(Context::new(&SHA256)),
+                Function::SHA512 => RingBackend::Ring(Context::new(&SHA512)),
+            }
+        }
+
+        fn update(&mut self, data: &[u8]) {
+            match *self {
+                RingBackend::MD5(ref mut hasher) => hasher.update(data),
+                RingBackend::Ring(ref mut context) => context.update(data),
+            }
+        }
+
+        fn finish(self) -> Vec<u8> {
+            match self {
+                RingBackend::MD5(hasher) => hasher.finalize().to_vec(),
+                RingBackend::Ring(context) => context.finish().as_ref().to_vec(),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "pure-rust-backend")]
+mod pure_rust_backend {
+    use alloc::vec::Vec;
+    use md5::Md5;
+    use sha2::{Digest, Sha256, Sha512};
+    use super::{Function, HashBackend};
+
+    pub enum PureRustBackend {
+        MD5(Md5),
+        SHA256(Sha256),
+        SHA512(Sha512),
+    }
+
+    impl HashBackend for PureRustBackend {
+        fn new(function: Function) -> PureRustBackend {
+            match function {
+                Function::MD5 => PureRustBackend::MD5(Md5::new()),
+                Function::SHA256 => PureRustBackend::SHA256(Sha256::new()),
+                Function::SHA512 => PureRustBackend::SHA512(Sha512::new()),
+            }
+        }
+
+        fn update(&mut self, data: &[u8]) {
+            match *self {
+                PureRustBackend::MD5(ref mut hasher) => hasher.update(data),
+                PureRustBackend::SHA256(ref mut hasher) => hasher.update(data),
+                PureRustBackend::SHA512(ref mut hasher) => hasher.update(data),
+            }
+        }
+
+        fn finish(self) -> Vec<u8> {
+            match self {
+                PureRustBackend::MD5(hasher) => hasher.finalize().to_vec(),
+                PureRustBackend::SHA256(hasher) => hasher.finalize().to_vec(),
+                PureRustBackend::SHA512(hasher) => hasher.finalize().to_vec(),
+            }
+        }
+    }
+}
@@ -19,18 +19,129 @@
 // THE SOFTWARE.
 
 //! Guardhaus is an HTTP authentication/authorization library.
+//!
+//! Without the (default) `std` feature, only the `core` module (and the `wasm` module, if the
+//! `wasm` feature is enabled) is available, which requires `alloc` and either the `ring-backend`
+//! or the `pure-rust-backend` feature.
 
+#![cfg_attr(not(any(feature = "std", feature = "wasm")), no_std)]
+
+extern crate alloc;
+#[cfg(feature = "reqwest-middleware")]
+extern crate async_trait;
+#[cfg(feature = "axum")]
+extern crate axum;
+#[cfg(feature = "std")]
+extern crate base64;
+#[cfg(feature = "crypto-hash")]
 extern crate crypto_hash;
+#[cfg(feature = "std")]
+extern crate getrandom;
+#[cfg(feature = "std")]
 extern crate hex;
+#[cfg(feature = "http")]
+extern crate http;
+#[cfg(feature = "std")]
 pub extern crate hyper;
+#[cfg(feature = "hyper1")]
+extern crate hyper1;
+#[cfg(feature = "logging")]
+extern crate log;
+#[cfg(any(feature = "ring-backend", feature = "pure-rust-backend"))]
+extern crate md5;
+#[cfg(feature = "tower")]
+extern crate pin_project_lite;
+#[cfg(all(test, feature = "std"))]
+#[macro_use]
+extern crate proptest;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "reqwest-middleware")]
+extern crate reqwest;
+#[cfg(feature = "reqwest-middleware")]
+extern crate reqwest_middleware;
+#[cfg(feature = "ring-backend")]
+extern crate ring;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "serde")]
+extern crate serde_json;
+#[cfg(feature = "pure-rust-backend")]
+extern crate sha2;
+#[cfg(feature = "saslprep")]
+extern crate stringprep;
+#[cfg(all(test, feature = "reqwest-middleware"))]
+extern crate tokio;
+#[cfg(all(test, feature = "tower"))]
+extern crate tokio_test;
+#[cfg(feature = "tower")]
+extern crate tower_layer;
+#[cfg(feature = "tower")]
+extern crate tower_service;
+#[cfg(all(test, feature = "tower"))]
+extern crate tower_test;
+#[cfg(feature = "std")]
 extern crate unicase;
+extern crate unicode_normalization;
+#[cfg(feature = "std")]
 extern crate url;
+#[cfg(feature = "wasm")]
+extern crate wasm_bindgen;
+#[cfg(all(test, feature = "reqwest-middleware"))]
+extern crate wiremock;
+extern crate zeroize;
+
+// Logs authentication events via the `log` crate if the `logging` feature is enabled. Otherwise,
+// the arguments are only type-checked, so that values which are only logged are still used.
+#[cfg(feature = "std")]
+macro_rules! log_event {
+    ($level:ident, $($arg:tt)+) => {{
+        #[cfg(feature = "logging")]
+        log::$level!($($arg)+);
+        #[cfg(not(feature = "logging"))]
+        let _ = format_args!($($arg)+);
+    }};
+}
 
+pub use crate::core::hash_value as digest_hash;
+
+#[cfg(feature = "std")]
 #[warn(missing_docs)]
 pub mod authentication_info;
+#[cfg(feature = "std")]
+#[warn(missing_docs)]
+pub mod challenge;
+#[cfg(feature = "reqwest-middleware")]
+#[warn(missing_docs)]
+pub mod client;
+#[warn(missing_docs)]
+pub mod core;
+#[cfg(feature = "std")]
 #[warn(missing_docs)]
 pub mod digest;
+#[cfg(feature = "std")]
+#[warn(missing_docs)]
+pub mod error;
+#[warn(missing_docs)]
+mod hash;
+#[cfg(feature = "std")]
+#[warn(missing_docs)]
+pub mod hyper_compat;
+#[cfg(feature = "tower")]
+#[warn(missing_docs)]
+pub mod middleware;
+#[cfg(feature = "std")]
 #[warn(missing_docs)]
 mod parsing;
+#[cfg(feature = "std")]
+#[warn(missing_docs)]
+pub mod responder;
+#[cfg(feature = "std")]
 #[warn(missing_docs)]
 pub mod types;
+#[cfg(feature = "std")]
+#[warn(missing_docs)]
+pub mod validator;
+#[cfg(feature = "wasm")]
+#[warn(missing_docs)]
+pub mod wasm;
@@ -0,0 +1,286 @@
+// Copyright (c) 2017 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! [Tower](https://crates.io/crates/tower) middleware which requires requests to be authenticated
+//! via `Digest` authorization headers.
+
+use getrandom::getrandom;
+use http::{HeaderValue, Request, Response, StatusCode};
+use http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use pin_project_lite::pin_project;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use super::challenge::Challenge;
+use super::digest::{Digest, parse_digest_header, validate_request_uri};
+use super::error::DigestError;
+use super::types::{HashAlgorithm, Qop};
+use super::validator::{CredentialStore, generate_server_nonce, unix_timestamp,
+                       validate_digest_using_ha1_fn};
+use tower_layer::Layer;
+use tower_service::Service;
+
+mod test;
+
+const KEY_LENGTH: usize = 32;
+const TIMESTAMP_LENGTH: usize = 16;
+const DEFAULT_MAX_NONCE_AGE: u64 = 300;
+
+fn generate_key() -> Option<Arc<[u8]>> {
+    let mut key = [0u8; KEY_LENGTH];
+    getrandom(&mut key).ok().map(|()| Arc::from(&key[..]))
+}
+
+enum NonceStatus {
+    Fresh,
+    Stale,
+    Invalid,
+}
+
+/// A `Layer` which wraps services in `DigestAuth` middleware.
+///
+/// Requests without a `Digest` authorization header receive a `401 Unauthorized` response, with a
+/// freshly generated challenge. Requests whose `Digest` does not validate against the credential
+/// store, or whose nonce was not issued by the layer, receive a `403 Forbidden` response.
+///
+/// Nonces are generated from a secret key and the current time (see `generate_server_nonce`), so
+/// they do not need to be stored. Valid `Digest`s whose nonce is older than the maximum nonce age
+/// receive a `401 Unauthorized` response with a `stale` challenge. Nonce counts are not tracked, so
+/// it is up to the application to protect against replay attacks within the maximum nonce age.
+/// Since the entity body is not buffered, only the `auth` quality of protection is supported.
+#[derive(Clone)]
+pub struct DigestAuthLayer {
+    realm: String,
+    algorithm: HashAlgorithm,
+    store: Arc<dyn CredentialStore + Send + Sync>,
+    // `None` if a random key could not be generated, in which case all requests are answered with
+    // a `500 Internal Server Error` response.
+    key: Option<Arc<[u8]>>,
+    max_nonce_age: u64,
+}
+
+impl DigestAuthLayer {
+    /// Creates a layer which authenticates requests for `realm` against the credentials in
+    /// `store`, using the SHA-256 algorithm, a random nonce key, and a maximum nonce age of five
+    /// minutes.
+    pub fn new(realm: String, store: Arc<dyn CredentialStore + Send + Sync>) -> DigestAuthLayer {
+        DigestAuthLayer {
+            realm,
+            algorithm: HashAlgorithm::SHA256,
+            store,
+            key: generate_key(),
+            max_nonce_age: DEFAULT_MAX_NONCE_AGE,
+        }
+    }
+
+    /// Sets the secret key which nonces are generated from, e.g. so that several servers accept
+    /// each other's nonces.
+    pub fn nonce_key(mut self, key: Vec<u8>) -> DigestAuthLayer {
+        self.key = Some(Arc::from(key));
+        self
+    }
+
+    /// Sets the number of seconds after which nonces are stale.
+    pub fn max_nonce_age(mut self, max_nonce_age: u64) -> DigestAuthLayer {
+        self.max_nonce_age = max_nonce_age;
+        self
+    }
+
+    /// Sets the algorithm which clients are challenged to use. The credential store must hash
+    /// its A1 strings with the same algorithm.
+    ///
+    /// Session variants are not supported, since their A1 strings depend on the client nonce.
+    pub fn algorithm(mut self, algorithm: HashAlgorithm) -> DigestAuthLayer {
+        self.algorithm = algorithm;
+        self
+    }
+}
+
+impl<S> Layer<S> for DigestAuthLayer {
+    type Service = DigestAuth<S>;
+
+    fn layer(&self, inner: S) -> DigestAuth<S> {
+        DigestAuth {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Middleware which only forwards requests with a valid `Digest` authorization header to the
+/// inner service. See `DigestAuthLayer` for details.
+#[derive(Clone)]
+pub struct DigestAuth<S> {
+    inner: S,
+    layer: DigestAuthLayer,
+}
+
+impl<S> DigestAuth<S> {
+    fn challenge(&self, key: &[u8], stale: bool) -> Challenge {
+        Challenge {
+            realm: self.layer.realm.clone(),
+            domain: vec![],
+            nonce: generate_server_nonce(key, unix_timestamp()),
+            opaque: None,
+            stale,
+            algorithm: self.layer.algorithm.clone(),
+            qop: vec![Qop::Auth],
+            charset: None,
+            userhash: false,
+        }
+    }
+
+    fn is_authorized<B>(&self, digest: &Digest, request: &Request<B>) -> bool {
+        if digest.realm != self.layer.realm || digest.algorithm != self.layer.algorithm ||
+            digest.qop == Some(Qop::AuthInt) ||
+            validate_request_uri(&digest.request_uri, &request.uri().to_string()).is_err()
+        {
+            return false;
+        }
+        validate_digest_using_ha1_fn(digest, request.method().as_str(), b"", |username, realm| {
+            self.layer.store.lookup_a1(username, realm)
+        }).is_ok()
+    }
+
+    fn check_nonce(&self, key: &[u8], nonce: &str) -> NonceStatus {
+        let timestamp = match nonce.get(..TIMESTAMP_LENGTH) {
+            Some(timestamp) => {
+                match u64::from_str_radix(timestamp, 16) {
+                    Ok(timestamp) => timestamp,
+                    Err(_) => return NonceStatus::Invalid,
+                }
+            }
+            None => return NonceStatus::Invalid,
+        };
+        let now = unix_timestamp();
+        if generate_server_nonce(key, timestamp) != nonce || timestamp > now {
+            NonceStatus::Invalid
+        } else if now - timestamp > self.layer.max_nonce_age {
+            NonceStatus::Stale
+        } else {
+            NonceStatus::Fresh
+        }
+    }
+
+    fn reject<B: Default>(&self, key: &[u8], status: StatusCode, stale: bool) -> Response<B> {
+        let mut response = Response::new(B::default());
+        *response.status_mut() = status;
+        if status == StatusCode::UNAUTHORIZED {
+            if let Ok(value) = HeaderValue::from_str(&self.challenge(key, stale).to_string()) {
+                response.headers_mut().insert(WWW_AUTHENTICATE, value);
+            }
+        }
+        response
+    }
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for DigestAuth<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    ResBody: Default,
+{
+    type Response = Response<ResBody>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future, ResBody>;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        let key = match self.layer.key {
+            Some(ref key) => key.clone(),
+            None => {
+                let mut response = Response::new(ResBody::default());
+                *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+                return ResponseFuture::rejected(response);
+            }
+        };
+        let (status, stale) = match request.headers().get(AUTHORIZATION) {
+            Some(value) => {
+                match parse_digest_header(value.as_bytes()) {
+                    Ok(ref digest) => {
+                        match self.check_nonce(&key, &digest.nonce) {
+                            NonceStatus::Fresh if self.is_authorized(digest, &request) => {
+                                return ResponseFuture::authorized(self.inner.call(request));
+                            }
+                            NonceStatus::Stale if self.is_authorized(digest, &request) => {
+                                (StatusCode::UNAUTHORIZED, true)
+                            }
+                            _ => (StatusCode::FORBIDDEN, false),
+                        }
+                    }
+                    Err(DigestError::MissingSchemePrefix) => (StatusCode::UNAUTHORIZED, false),
+                    Err(_) => (StatusCode::FORBIDDEN, false),
+                }
+            }
+            None => (StatusCode::UNAUTHORIZED, false),
+        };
+        ResponseFuture::rejected(self.reject(&key, status, stale))
+    }
+}
+
+pin_project! {
+    /// The response future of `DigestAuth`.
+    pub struct ResponseFuture<F, B> {
+        #[pin]
+        kind: Kind<F, B>,
+    }
+}
+
+pin_project! {
+    #[project = KindProj]
+    enum Kind<F, B> {
+        Authorized {
+            #[pin]
+            future: F,
+        },
+        Rejected {
+            response: Option<Response<B>>,
+        },
+    }
+}
+
+impl<F, B> ResponseFuture<F, B> {
+    fn authorized(future: F) -> ResponseFuture<F, B> {
+        ResponseFuture { kind: Kind::Authorized { future } }
+    }
+
+    fn rejected(response: Response<B>) -> ResponseFuture<F, B> {
+        ResponseFuture { kind: Kind::Rejected { response: Some(response) } }
+    }
+}
+
+impl<F, B, E> Future for ResponseFuture<F, B>
+where
+    F: Future<Output = Result<Response<B>, E>>,
+{
+    type Output = Result<Response<B>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        match self.project().kind.project() {
+            KindProj::Authorized { future } => future.poll(cx),
+            KindProj::Rejected { response } => {
+                Poll::Ready(Ok(response.take().expect("Polled after completion")))
+            }
+        }
+    }
+}
@@ -0,0 +1,233 @@
+// Copyright (c) 2017 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+#![cfg(test)]
+
+use http::{Request, Response, StatusCode};
+use http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use std::sync::Arc;
+use std::task::Poll;
+use super::DigestAuthLayer;
+use super::super::digest::test_helper::rfc7616_digest_header;
+use super::super::types::HashAlgorithm;
+use super::super::validator::{InMemoryCredentialStore, generate_server_nonce, unix_timestamp};
+use tokio_test::task;
+use tower_test::mock;
+
+const REALM: &str = "http-auth@example.org";
+const KEY: &[u8] = b"secret";
+const RFC7616_SHA256_RESPONSE: &str =
+    "753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1";
+
+fn layer(password: &str) -> DigestAuthLayer {
+    let mut store = InMemoryCredentialStore::new();
+    store.insert_password(
+        &HashAlgorithm::SHA256,
+        "Mufasa".to_owned(),
+        REALM.to_owned(),
+        password,
+    );
+    DigestAuthLayer::new(REALM.to_owned(), Arc::new(store)).nonce_key(KEY.to_vec())
+}
+
+fn request(authorization: Option<String>) -> Request<()> {
+    let mut builder = Request::builder().method("GET").uri("/dir/index.html");
+    if let Some(authorization) = authorization {
+        builder = builder.header(AUTHORIZATION, authorization);
+    }
+    builder.body(()).unwrap()
+}
+
+fn rfc7616_authorization() -> Option<String> {
+    Some(rfc7616_digest_header(HashAlgorithm::SHA256, RFC7616_SHA256_RESPONSE).to_string())
+}
+
+// The RFC 7616 example, for `uri` and with a nonce issued at `timestamp`.
+fn authorization_for_uri(uri: &str, timestamp: u64) -> Option<String> {
+    let mut digest = rfc7616_digest_header(HashAlgorithm::SHA256, "");
+    digest.request_uri = uri.to_owned();
+    digest.nonce = generate_server_nonce(KEY, timestamp);
+    digest.response = digest
+        .using_password("GET", b"", "Circle of Life".to_owned())
+        .expect("Could not generate response");
+    Some(digest.to_string())
+}
+
+fn authorization_with_nonce_timestamp(timestamp: u64) -> Option<String> {
+    authorization_for_uri("/dir/index.html", timestamp)
+}
+
+fn authorization() -> Option<String> {
+    authorization_with_nonce_timestamp(unix_timestamp())
+}
+
+fn assert_rejected(layer: DigestAuthLayer, request: Request<()>) -> Response<String> {
+    let (mut service, mut handle) = mock::spawn_layer::<Request<()>, Response<String>, _>(layer);
+    assert!(service.poll_ready().is_ready());
+    let mut response = task::spawn(service.call(request));
+    assert!(handle.poll_request().is_pending());
+    match response.poll() {
+        Poll::Ready(Ok(response)) => response,
+        _ => panic!("Expected a rejection response"),
+    }
+}
+
+#[test]
+fn test_forwards_authorized_request() {
+    let (mut service, mut handle) =
+        mock::spawn_layer::<Request<()>, Response<String>, _>(layer("Circle of Life"));
+    assert!(service.poll_ready().is_ready());
+    let mut response = task::spawn(service.call(request(authorization())));
+    assert!(response.poll().is_pending());
+
+    match handle.poll_request() {
+        Poll::Ready(Some((forwarded, send_response))) => {
+            assert_eq!("/dir/index.html", forwarded.uri());
+            send_response.send_response(Response::new("Welcome".to_owned()));
+        }
+        _ => panic!("Expected the request to be forwarded"),
+    }
+
+    match response.poll() {
+        Poll::Ready(Ok(response)) => {
+            assert_eq!(StatusCode::OK, response.status());
+            assert_eq!("Welcome", response.body());
+        }
+        _ => panic!("Expected the inner service's response"),
+    }
+}
+
+#[test]
+fn test_forwards_request_with_percent_encoded_uri() {
+    let (mut service, mut handle) =
+        mock::spawn_layer::<Request<()>, Response<String>, _>(layer("Circle of Life"));
+    assert!(service.poll_ready().is_ready());
+    let authorization = authorization_for_uri("/dir/%69ndex.html", unix_timestamp());
+    let mut response = task::spawn(service.call(request(authorization)));
+    assert!(response.poll().is_pending());
+    assert!(handle.poll_request().is_ready());
+}
+
+#[test]
+fn test_challenges_request_without_authorization() {
+    let response = assert_rejected(layer("Circle of Life"), request(None));
+    assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+    let challenge = response.headers()[WWW_AUTHENTICATE].to_str().unwrap();
+    assert!(challenge.starts_with("Digest realm=\"http-auth@example.org\", nonce=\""));
+    assert!(challenge.contains("algorithm=SHA-256"));
+}
+
+#[test]
+fn test_challenges_request_with_other_scheme() {
+    let authorization = Some("Basic TXVmYXNhOkNpcmNsZSBvZiBMaWZl".to_owned());
+    let response = assert_rejected(layer("Circle of Life"), request(authorization));
+    assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+    assert!(response.headers().contains_key(WWW_AUTHENTICATE));
+}
+
+#[test]
+fn test_forbids_incorrect_password() {
+    let response = assert_rejected(layer("Circle of Death"), request(authorization()));
+    assert_eq!(StatusCode::FORBIDDEN, response.status());
+    assert!(!response.headers().contains_key(WWW_AUTHENTICATE));
+}
+
+#[test]
+fn test_forbids_unknown_username() {
+    let layer = DigestAuthLayer::new(REALM.to_owned(), Arc::new(InMemoryCredentialStore::new()));
+    let response = assert_rejected(layer, request(authorization()));
+    assert_eq!(StatusCode::FORBIDDEN, response.status());
+}
+
+#[test]
+fn test_forbids_other_realm() {
+    let mut store = InMemoryCredentialStore::new();
+    store.insert_password(
+        &HashAlgorithm::SHA256,
+        "Mufasa".to_owned(),
+        REALM.to_owned(),
+        "Circle of Life",
+    );
+    let layer = DigestAuthLayer::new("api@example.org".to_owned(), Arc::new(store));
+    let response = assert_rejected(layer, request(authorization()));
+    assert_eq!(StatusCode::FORBIDDEN, response.status());
+}
+
+#[test]
+fn test_forbids_other_algorithm() {
+    let layer = layer("Circle of Life").algorithm(HashAlgorithm::SHA512256);
+    let response = assert_rejected(layer, request(authorization()));
+    assert_eq!(StatusCode::FORBIDDEN, response.status());
+}
+
+#[test]
+fn test_forbids_malformed_digest() {
+    let authorization = Some("Digest realm=\"http-auth@example.org\"".to_owned());
+    let response = assert_rejected(layer("Circle of Life"), request(authorization));
+    assert_eq!(StatusCode::FORBIDDEN, response.status());
+}
+
+#[test]
+fn test_forbids_nonce_not_issued_by_layer() {
+    let response = assert_rejected(layer("Circle of Life"), request(rfc7616_authorization()));
+    assert_eq!(StatusCode::FORBIDDEN, response.status());
+}
+
+#[test]
+fn test_forbids_nonce_issued_with_other_key() {
+    let layer = layer("Circle of Life").nonce_key(b"other secret".to_vec());
+    let response = assert_rejected(layer, request(authorization()));
+    assert_eq!(StatusCode::FORBIDDEN, response.status());
+}
+
+#[test]
+fn test_forbids_nonce_from_the_future() {
+    let authorization = authorization_with_nonce_timestamp(unix_timestamp() + 3600);
+    let response = assert_rejected(layer("Circle of Life"), request(authorization));
+    assert_eq!(StatusCode::FORBIDDEN, response.status());
+}
+
+#[test]
+fn test_challenges_stale_nonce() {
+    let layer = layer("Circle of Life").max_nonce_age(60);
+    let authorization = authorization_with_nonce_timestamp(unix_timestamp() - 120);
+    let response = assert_rejected(layer, request(authorization));
+    assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+    let challenge = response.headers()[WWW_AUTHENTICATE].to_str().unwrap();
+    assert!(challenge.contains("stale=true"));
+}
+
+#[test]
+fn test_forbids_stale_nonce_with_incorrect_password() {
+    let layer = layer("Circle of Death").max_nonce_age(60);
+    let authorization = authorization_with_nonce_timestamp(unix_timestamp() - 120);
+    let response = assert_rejected(layer, request(authorization));
+    assert_eq!(StatusCode::FORBIDDEN, response.status());
+}
+
+#[test]
+fn test_fails_without_nonce_key() {
+    let layer = DigestAuthLayer {
+        key: None,
+        ..layer("Circle of Life")
+    };
+    let response = assert_rejected(layer, request(authorization()));
+    assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, response.status());
+}
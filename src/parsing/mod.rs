@@ -20,50 +20,146 @@
 
 //! Utility functions to parse headers.
 
-use hyper::header::Raw;
-use hyper::header::parsing::from_comma_delimited;
-use std::collections::HashMap;
+use hyper::Error;
 use unicase::UniCase;
 use url::percent_encoding::percent_decode;
 
+pub use super::core::append_parameter;
+
 pub mod test_helper;
 
-/// Append a header parameter to a serialized header.
-pub fn append_parameter(serialized: &mut String, key: &str, value: &str, quoted: bool) {
-    if !serialized.is_empty() {
-        serialized.push_str(", ")
-    }
-    serialized.push_str(key);
-    serialized.push_str("=");
-    if quoted {
-        serialized.push_str("\"");
+/// Split a serialized header into its parameters, ignoring commas inside of quoted strings.
+fn split_parameters(s: &str) -> Vec<&str> {
+    let mut parameters = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (index, character) in s.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match character {
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                parameters.push(&s[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
     }
-    serialized.push_str(value);
-    if quoted {
-        serialized.push_str("\"");
+    parameters.push(&s[start..]);
+
+    parameters
+        .into_iter()
+        .map(str::trim)
+        .filter(|parameter| !parameter.is_empty())
+        .collect()
+}
+
+/// Header parameters as (name, value) pairs borrowed from the serialized header, in the order
+/// they appear in the header. Quoted-string values keep their double quotes, so that they can be
+/// distinguished from tokens.
+///
+/// Headers have few parameters, so a linear scan via `get_param` is cheaper than building a map.
+pub type Parameters<'a> = Vec<(&'a str, &'a str)>;
+
+/// Splits a serialized header into its parameters. Fails if a value is only partially quoted,
+/// e.g. `qop="auth`.
+pub fn parse_parameters<'a>(s: &'a str) -> Result<Parameters<'a>, Error> {
+    split_parameters(s)
+        .into_iter()
+        .map(|parameter| {
+            let mut parts = parameter.splitn(2, '=');
+            let name = parts.next().unwrap_or("").trim();
+            let value = parts.next().unwrap_or("").trim();
+            match unquote(value) {
+                Some(_) => Ok((name, value)),
+                None => Err(Error::Header),
+            }
+        })
+        .collect()
+}
+
+/// Whether a parameter of a serialized header has whitespace before or after its `=`, which is
+/// tolerated by `parse_parameters`.
+pub fn has_whitespace_around_equals(s: &str) -> bool {
+    split_parameters(s).into_iter().any(|parameter| {
+        let mut parts = parameter.splitn(2, '=');
+        let name = parts.next().unwrap_or("");
+        let value = parts.next().unwrap_or("");
+        name.ends_with(char::is_whitespace) || value.starts_with(char::is_whitespace)
+    })
+}
+
+/// Whether a raw parameter value is a quoted string, i.e. whether it starts with a double quote
+/// and ends with an unescaped double quote.
+pub fn is_quoted(value: &str) -> bool {
+    if value.len() < 2 || !value.starts_with('"') || !value.ends_with('"') {
+        return false;
     }
+    let escapes = value[1..value.len() - 1]
+        .bytes()
+        .rev()
+        .take_while(|&byte| byte == b'\\')
+        .count();
+    escapes % 2 == 0
 }
 
-pub fn parse_parameters(s: &str) -> HashMap<UniCase<String>, String> {
-    let parameters: Vec<String> =
-        from_comma_delimited(&Raw::from(s)).expect("Could not parse header parameters");
-    let mut param_map: HashMap<UniCase<String>, String> = HashMap::with_capacity(parameters.len());
-    for parameter in parameters {
-        let parts: Vec<&str> = parameter.splitn(2, '=').collect();
-        param_map.insert(
-            UniCase::new(parts[0].trim().to_owned()),
-            parts[1].trim().trim_matches('"').to_owned(),
-        );
+/// Strips the double quotes from a quoted-string value. Returns `None` if the value is only
+/// partially quoted.
+fn unquote(value: &str) -> Option<&str> {
+    if is_quoted(value) {
+        Some(&value[1..value.len() - 1])
+    } else if value.contains('"') {
+        None
+    } else {
+        Some(value)
     }
+}
+
+/// Looks up the raw value of a parameter by its case-insensitive name, including the double
+/// quotes of a quoted string. If the parameter is specified more than once, the last value wins.
+pub fn get_raw_param<'a>(params: &[(&'a str, &'a str)], key: &str) -> Option<&'a str> {
+    params
+        .iter()
+        .rev()
+        .find(|parameter| parameter.0.eq_ignore_ascii_case(key))
+        .map(|parameter| parameter.1)
+}
 
-    param_map
+/// Looks up the value of a parameter by its case-insensitive name, without the double quotes of a
+/// quoted string. If the parameter is specified more than once, the last value wins.
+pub fn get_param<'a>(params: &[(&'a str, &'a str)], key: &str) -> Option<&'a str> {
+    get_raw_param(params, key).and_then(unquote)
 }
 
-pub fn unraveled_map_value(map: &HashMap<UniCase<String>, String>, key: &str) -> Option<String> {
-    let value = match map.get(&UniCase::new(key.to_owned())) {
-        Some(v) => v,
-        None => return None,
-    };
+/// Strips a case-insensitive authentication scheme (and the whitespace following it) from the
+/// beginning of a header value. Returns `None` if the header value does not use `scheme`.
+pub fn strip_scheme<'a>(s: &'a str, scheme: &str) -> Option<&'a str> {
+    let s = s.trim_start();
+    match s.get(..scheme.len()) {
+        Some(prefix) if UniCase::new(prefix) == UniCase::new(scheme) => {
+            let rest = &s[scheme.len()..];
+            if rest.starts_with(' ') {
+                Some(rest.trim_start())
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Looks up the value of a parameter by its case-insensitive name. Quoted-string values are
+/// returned verbatim (without their double quotes), since percent-encoding is only meaningful in
+/// token values, which are percent-decoded.
+pub fn unraveled_param_value(params: &[(&str, &str)], key: &str) -> Option<String> {
+    let value = get_raw_param(params, key)?;
+    if is_quoted(value) {
+        return unquote(value).map(str::to_owned);
+    }
     match percent_decode(value.as_bytes()).decode_utf8() {
         Ok(string) => Some(string.into_owned()),
         Err(_) => None,
@@ -0,0 +1,431 @@
+// Copyright (c) 2017 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Client-side generation of `Authorization` headers in response to a `WWW-Authenticate`
+//! challenge, and caching of those challenges.
+
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use getrandom::getrandom;
+use hyper::header::Charset;
+use hyper::header::parsing::ExtendedValue;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use super::challenge::{Challenge, parse_challenges};
+use super::core::generate_kd;
+use super::digest::{Digest, Username};
+use super::error::DigestError;
+use super::types::{HashAlgorithm, NonceCount, Qop};
+use zeroize::Zeroizing;
+
+mod test;
+
+/// The number of random bytes in a client nonce generated by `generate_cnonce`.
+const CLIENT_NONCE_LENGTH: usize = 16;
+
+struct Response {
+    digest: Digest,
+    method: String,
+    body_hash: String,
+    simple_hashed_a1: Zeroizing<String>,
+    hashed_a1: Zeroizing<String>,
+}
+
+/// Which algorithm to answer when a server offers challenges for several algorithms.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum AlgorithmPreference {
+    /// The algorithm with the most `security_bits`; ties are broken by the order of
+    /// `HashAlgorithm`. This is the default.
+    #[default]
+    Strongest,
+    /// The algorithm with the fewest `security_bits`, e.g. for servers whose strongest algorithm
+    /// is broken.
+    Weakest,
+    /// The first algorithm in the list which is offered, or the strongest one if none is.
+    Preferred(Vec<HashAlgorithm>),
+}
+
+fn compare_strength(a: &Challenge, b: &Challenge) -> Ordering {
+    a.algorithm.cmp(&b.algorithm)
+}
+
+impl AlgorithmPreference {
+    /// Selects the challenge to answer from the challenges offered by a server, or `None` if no
+    /// challenges were offered.
+    pub fn select<'a>(&self, challenges: &'a [Challenge]) -> Option<&'a Challenge> {
+        match *self {
+            AlgorithmPreference::Strongest => {
+                challenges.iter().max_by(|a, b| compare_strength(a, b))
+            }
+            AlgorithmPreference::Weakest => challenges.iter().min_by(|a, b| compare_strength(a, b)),
+            AlgorithmPreference::Preferred(ref algorithms) => {
+                algorithms
+                    .iter()
+                    .filter_map(|algorithm| {
+                        challenges.iter().find(|challenge| challenge.algorithm == *algorithm)
+                    })
+                    .next()
+                    .or_else(|| AlgorithmPreference::Strongest.select(challenges))
+            }
+        }
+    }
+}
+
+/// Answers the strongest `Digest` challenge in a `WWW-Authenticate` header value, returning the
+/// value of the `Authorization` header for the request, including the `Digest` scheme.
+///
+/// The response is generated by `ChallengeResponder::generate_response`. Returns
+/// `DigestError::MissingSchemePrefix` if the header value has no valid `Digest` challenge.
+pub fn respond_to_401(
+    www_authenticate_value: &str,
+    username: &str,
+    password: &str,
+    method: &str,
+    uri: &str,
+    entity_body: &[u8],
+) -> Result<String, DigestError> {
+    respond_to_401_with_algorithm_preference(
+        www_authenticate_value,
+        username,
+        password,
+        method,
+        uri,
+        entity_body,
+        AlgorithmPreference::Strongest,
+    )
+}
+
+/// Like `respond_to_401`, but answers the `Digest` challenge selected by `preference`.
+pub fn respond_to_401_with_algorithm_preference(
+    www_authenticate_value: &str,
+    username: &str,
+    password: &str,
+    method: &str,
+    uri: &str,
+    entity_body: &[u8],
+    preference: AlgorithmPreference,
+) -> Result<String, DigestError> {
+    let challenges = parse_challenges(www_authenticate_value);
+    let mut responder =
+        ChallengeResponder::from_challenges(challenges)?.with_algorithm_preference(preference);
+    let digest = responder.generate_response(username, password, method, uri, entity_body)?;
+    Ok(digest.to_string())
+}
+
+/// Generates `Digest` headers that answer a server's `Challenge`.
+///
+/// The password is not retained; only the hashed A1 value is kept, so that subsequent requests
+/// can reuse the same nonce via `increment`.
+pub struct ChallengeResponder {
+    challenge: Challenge,
+    offered: Vec<Challenge>,
+    last_response: Option<Response>,
+}
+
+/// Generates a client nonce (`cnonce`) from 16 bytes of the operating system's random number
+/// generator, encoded as unpadded base64url so that it only contains token characters.
+///
+/// # Panics
+///
+/// Panics if the random number generator is unavailable. Use `generate_cnonce_bytes` to handle
+/// that case instead.
+pub fn generate_cnonce() -> String {
+    try_generate_cnonce().expect("Could not generate a client nonce")
+}
+
+// Like `generate_cnonce`, but returns `DigestError::RandomUnavailable` instead of panicking.
+fn try_generate_cnonce() -> Result<String, DigestError> {
+    let mut bytes = [0u8; CLIENT_NONCE_LENGTH];
+    generate_cnonce_bytes(&mut bytes)?;
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+/// Fills `buf` with bytes from the operating system's random number generator, for callers
+/// which encode the client nonce themselves.
+pub fn generate_cnonce_bytes(buf: &mut [u8]) -> Result<(), DigestError> {
+    getrandom(buf).map_err(|_| DigestError::RandomUnavailable)
+}
+
+fn session_hashed_a1(
+    digest: &Digest,
+    simple_hashed_a1: &str,
+) -> Result<Zeroizing<String>, DigestError> {
+    if !digest.algorithm.is_session() {
+        return Ok(Zeroizing::new(simple_hashed_a1.to_owned()));
+    }
+    match digest.client_nonce {
+        Some(ref client_nonce) => {
+            let data = format!("{}:{}", digest.nonce, client_nonce);
+            Ok(Zeroizing::new(generate_kd(&digest.algorithm, simple_hashed_a1, &data)))
+        }
+        None => Err(DigestError::MissingClientNonce),
+    }
+}
+
+fn encode_username(username: &str) -> Username {
+    if username.is_ascii() {
+        Username::Plain(username.to_owned())
+    } else {
+        Username::Encoded(ExtendedValue {
+            charset: Charset::Ext("UTF-8".to_owned()),
+            language_tag: None,
+            value: username.as_bytes().to_vec(),
+        })
+    }
+}
+
+impl ChallengeResponder {
+    /// Creates a responder for the given challenge.
+    pub fn new(challenge: Challenge) -> ChallengeResponder {
+        ChallengeResponder {
+            offered: vec![challenge.clone()],
+            challenge,
+            last_response: None,
+        }
+    }
+
+    /// Creates a responder for the strongest of the challenges offered by a server, e.g. via
+    /// `challenge::parse_challenges`. Use `with_algorithm_preference` to select another one.
+    ///
+    /// Returns `DigestError::MissingSchemePrefix` if `challenges` is empty.
+    pub fn from_challenges(challenges: Vec<Challenge>) -> Result<ChallengeResponder, DigestError> {
+        let challenge = AlgorithmPreference::Strongest
+            .select(&challenges)
+            .cloned()
+            .ok_or(DigestError::MissingSchemePrefix)?;
+        Ok(ChallengeResponder {
+            challenge,
+            offered: challenges,
+            last_response: None,
+        })
+    }
+
+    /// Selects which of the offered challenges to answer, by algorithm. This discards the
+    /// previously generated response, if any.
+    pub fn with_algorithm_preference(
+        mut self,
+        preference: AlgorithmPreference,
+    ) -> ChallengeResponder {
+        if let Some(challenge) = preference.select(&self.offered) {
+            self.challenge = challenge.clone();
+        }
+        self.last_response = None;
+        self
+    }
+
+    /// The challenge that is being responded to.
+    pub fn challenge(&self) -> &Challenge {
+        &self.challenge
+    }
+
+    fn select_qop(&self) -> Option<Qop> {
+        if self.challenge.qop.contains(&Qop::Auth) {
+            Some(Qop::Auth)
+        } else {
+            self.challenge.qop.first().cloned()
+        }
+    }
+
+    /// Generates a `Digest` for a request, with a random `cnonce` and a nonce count of 1.
+    ///
+    /// `auth` is preferred over `auth-int` when the challenge offers both. If the challenge
+    /// supports userhashes, the username is sent as a userhash; otherwise, non-ASCII usernames
+    /// are sent via the `username*` parameter.
+    ///
+    /// `method` is the method of the HTTP request, e.g. `GET`.
+    pub fn generate_response(
+        &mut self,
+        username: &str,
+        password: &str,
+        method: &str,
+        uri: &str,
+        entity_body: &[u8],
+    ) -> Result<Digest, DigestError> {
+        if method.is_empty() {
+            return Err(DigestError::MissingMethod);
+        }
+        let qop = self.select_qop();
+        let client_nonce = if qop.is_some() || self.challenge.algorithm.is_session() {
+            Some(try_generate_cnonce()?)
+        } else {
+            None
+        };
+        let username = encode_username(username);
+        let header_username = if self.challenge.userhash {
+            Username::Plain(Digest::userhash(
+                &self.challenge.algorithm,
+                &username,
+                &self.challenge.realm,
+            ))
+        } else {
+            username.clone()
+        };
+        let body_hash = match qop {
+            Some(Qop::AuthInt) => self.challenge.algorithm.hex_digest(entity_body),
+            _ => String::new(),
+        };
+        let digest = Digest {
+            username: header_username,
+            realm: self.challenge.realm.clone(),
+            nonce: self.challenge.nonce.clone(),
+            nonce_count: qop.as_ref().map(|_| NonceCount(1)),
+            response: String::new(),
+            request_uri: uri.to_owned(),
+            algorithm: self.challenge.algorithm.clone(),
+            qop,
+            client_nonce,
+            opaque: self.challenge.opaque.clone(),
+            charset: self.challenge.charset.clone(),
+            userhash: self.challenge.userhash,
+            method: Some(method.to_owned()),
+        };
+        let simple_hashed_a1 = Zeroizing::new(
+            digest
+                .algorithm
+                .hex_digest(&digest.simple_a1_using_bytes(username.as_bytes(), password)?),
+        );
+        let hashed_a1 = session_hashed_a1(&digest, &simple_hashed_a1)?;
+
+        self.respond(Response {
+            digest,
+            method: method.to_owned(),
+            body_hash,
+            simple_hashed_a1,
+            hashed_a1,
+        })
+    }
+
+    /// Retries the previous request with the nonce of a stale `challenge`, i.e. one that was sent
+    /// because the previous nonce expired, without requiring the password again. The new challenge
+    /// replaces the current one.
+    ///
+    /// Returns an error if `generate_response` has not been called yet, or
+    /// `DigestError::CredentialsRequired` if the challenge is not stale, or changes the realm or
+    /// algorithm.
+    pub fn respond_to_stale_challenge(
+        &mut self,
+        challenge: Challenge,
+    ) -> Result<Digest, DigestError> {
+        let mut response = match self.last_response.take() {
+            Some(response) => response,
+            None => return Err(DigestError::NoPreviousResponse),
+        };
+        if !challenge.is_stale() || challenge.realm != response.digest.realm ||
+            challenge.algorithm != response.digest.algorithm
+        {
+            self.last_response = Some(response);
+            return Err(DigestError::CredentialsRequired);
+        }
+        response.digest.nonce = challenge.nonce.clone();
+        response.digest.opaque = challenge.opaque.clone();
+        if response.digest.client_nonce.is_some() {
+            match try_generate_cnonce() {
+                Ok(client_nonce) => response.digest.client_nonce = Some(client_nonce),
+                Err(err) => {
+                    self.last_response = Some(response);
+                    return Err(err);
+                }
+            }
+        }
+        if response.digest.nonce_count.is_some() {
+            response.digest.nonce_count = Some(NonceCount(1));
+        }
+        response.hashed_a1 = session_hashed_a1(&response.digest, &response.simple_hashed_a1)?;
+        self.challenge = challenge;
+
+        self.respond(response)
+    }
+
+    /// Generates a `Digest` for the next request that reuses the server nonce, by incrementing
+    /// the nonce count of the previously generated response.
+    ///
+    /// Returns an error if `generate_response` has not been called yet.
+    pub fn increment(&mut self) -> Result<Digest, DigestError> {
+        let mut response = match self.last_response.take() {
+            Some(response) => response,
+            None => return Err(DigestError::NoPreviousResponse),
+        };
+        if let Some(NonceCount(count)) = response.digest.nonce_count {
+            response.digest.nonce_count = Some(NonceCount(count.wrapping_add(1)));
+        }
+
+        self.respond(response)
+    }
+
+    fn respond(&mut self, mut response: Response) -> Result<Digest, DigestError> {
+        response.digest.response = response.digest.using_hashed_a1_and_body_hash(
+            &response.method,
+            &response.body_hash,
+            (*response.hashed_a1).clone(),
+        )?;
+        let digest = response.digest.clone();
+        self.last_response = Some(response);
+
+        Ok(digest)
+    }
+}
+
+/// Caches challenges per host and realm, so that clients can authenticate preemptively instead of
+/// waiting for a `401` response to every request. See
+/// [RFC 2617, section 3.3](https://tools.ietf.org/html/rfc2617#section-3.3).
+#[derive(Clone, Debug, Default)]
+pub struct ChallengeCache {
+    // The expiry is `None` if the challenge never expires.
+    challenges: HashMap<(String, String), (Challenge, Option<Instant>)>,
+}
+
+fn is_live(expiry: Option<Instant>, now: Instant) -> bool {
+    expiry.is_none_or(|expiry| now < expiry)
+}
+
+impl ChallengeCache {
+    /// Creates an empty cache.
+    pub fn new() -> ChallengeCache {
+        ChallengeCache::default()
+    }
+
+    /// Returns the cached challenge for a host and realm, unless it has expired.
+    pub fn get(&self, host: &str, realm: &str) -> Option<&Challenge> {
+        match self.challenges.get(&(host.to_owned(), realm.to_owned())) {
+            Some(&(ref challenge, expiry)) if is_live(expiry, Instant::now()) => Some(challenge),
+            _ => None,
+        }
+    }
+
+    /// Caches a challenge for a host, replacing any existing challenge for the same realm, and
+    /// removes expired challenges. The challenge expires after `ttl`, or never if `ttl` is too
+    /// large to be represented, e.g. `Duration::MAX`.
+    pub fn insert(&mut self, host: &str, challenge: Challenge, ttl: Duration) {
+        let now = Instant::now();
+        self.purge_expired_at(now);
+        let key = (host.to_owned(), challenge.realm.clone());
+        self.challenges.insert(key, (challenge, now.checked_add(ttl)));
+    }
+
+    /// Removes expired challenges.
+    pub fn purge_expired(&mut self) {
+        self.purge_expired_at(Instant::now());
+    }
+
+    fn purge_expired_at(&mut self, now: Instant) {
+        self.challenges.retain(|_, &mut (_, expiry)| is_live(expiry, now));
+    }
+}
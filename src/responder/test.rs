@@ -0,0 +1,430 @@
+// Copyright (c) 2017 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+#![cfg(test)]
+
+use std::time::Duration;
+use super::{AlgorithmPreference, ChallengeCache, ChallengeResponder, generate_cnonce,
+            generate_cnonce_bytes, respond_to_401, respond_to_401_with_algorithm_preference};
+use super::super::challenge::{Challenge, generate_stale_challenge};
+use super::super::challenge::test_helper::rfc7616_challenge;
+use super::super::digest::{Username, parse_digest_header};
+use super::super::error::{DigestError, ValidationFailure};
+use super::super::types::{HashAlgorithm, NonceCount, Qop};
+
+const USERNAME: &str = "Mufasa";
+const PASSWORD: &str = "Circle of Life";
+
+#[test]
+fn test_generate_response() {
+    let challenge = rfc7616_challenge(HashAlgorithm::SHA256);
+    let mut responder = ChallengeResponder::new(challenge.clone());
+    let digest = responder
+        .generate_response(USERNAME, PASSWORD, "GET", "/dir/index.html", b"")
+        .expect("Could not generate response");
+
+    assert_eq!(Username::Plain(USERNAME.to_owned()), digest.username);
+    assert_eq!(challenge.realm, digest.realm);
+    assert_eq!(challenge.nonce, digest.nonce);
+    assert_eq!(challenge.opaque, digest.opaque);
+    assert_eq!("/dir/index.html", digest.request_uri);
+    assert_eq!(Some(NonceCount(1)), digest.nonce_count);
+    assert_eq!(Some(Qop::Auth), digest.qop);
+    assert_eq!(Some(22), digest.client_nonce.as_ref().map(|cnonce| cnonce.len()));
+    assert!(digest.is_valid_hex_response());
+    assert_eq!(Ok(()), digest.validate_using_password("GET", b"", PASSWORD.to_owned()))
+}
+
+#[allow(deprecated)]
+#[test]
+fn test_generate_response_uses_random_client_nonces() {
+    let mut responder = ChallengeResponder::new(rfc7616_challenge(HashAlgorithm::MD5));
+    let first = responder
+        .generate_response(USERNAME, PASSWORD, "GET", "/", b"")
+        .expect("Could not generate response");
+    let second = responder
+        .generate_response(USERNAME, PASSWORD, "GET", "/", b"")
+        .expect("Could not generate response");
+
+    assert!(first.client_nonce != second.client_nonce)
+}
+
+#[test]
+fn test_generate_response_with_auth_int_qop() {
+    let mut challenge = rfc7616_challenge(HashAlgorithm::SHA256Session);
+    challenge.qop = vec![Qop::AuthInt];
+    let mut responder = ChallengeResponder::new(challenge);
+    let digest = responder
+        .generate_response(USERNAME, PASSWORD, "POST", "/upload", b"foo=bar")
+        .expect("Could not generate response");
+
+    assert_eq!(Some(Qop::AuthInt), digest.qop);
+    assert_eq!(
+        Ok(()),
+        digest.validate_using_password("POST", b"foo=bar", PASSWORD.to_owned())
+    );
+    assert_eq!(
+        Err(ValidationFailure::ResponseMismatch),
+        digest.validate_using_password("POST", b"foo=baz", PASSWORD.to_owned())
+    )
+}
+
+#[allow(deprecated)]
+#[test]
+fn test_generate_response_sans_qop() {
+    let mut challenge = rfc7616_challenge(HashAlgorithm::MD5);
+    challenge.qop = vec![];
+    let mut responder = ChallengeResponder::new(challenge);
+    let digest = responder
+        .generate_response(USERNAME, PASSWORD, "GET", "/", b"")
+        .expect("Could not generate response");
+
+    assert_eq!(None, digest.qop);
+    assert_eq!(None, digest.nonce_count);
+    assert_eq!(None, digest.client_nonce);
+    assert_eq!(Ok(()), digest.validate_using_password("GET", b"", PASSWORD.to_owned()))
+}
+
+#[test]
+fn test_generate_response_with_userhash() {
+    let mut challenge = rfc7616_challenge(HashAlgorithm::SHA512256);
+    challenge.userhash = true;
+    let mut responder = ChallengeResponder::new(challenge);
+    let digest = responder
+        .generate_response(USERNAME, PASSWORD, "GET", "/", b"")
+        .expect("Could not generate response");
+
+    assert!(digest.userhash);
+    assert_eq!(
+        Ok(true),
+        digest.validate_userhash(&Username::Plain(USERNAME.to_owned()))
+    );
+    assert_eq!(
+        Ok(()),
+        digest.validate_using_userhash_and_password(
+            "GET",
+            b"",
+            Username::Plain(USERNAME.to_owned()),
+            PASSWORD.to_owned(),
+        )
+    )
+}
+
+#[test]
+fn test_generate_response_without_method() {
+    let mut responder = ChallengeResponder::new(rfc7616_challenge(HashAlgorithm::SHA256));
+    assert_eq!(
+        Err(DigestError::MissingMethod),
+        responder.generate_response(USERNAME, PASSWORD, "", "/", b"")
+    );
+}
+
+#[test]
+fn test_generate_response_with_non_ascii_username() {
+    let mut responder = ChallengeResponder::new(rfc7616_challenge(HashAlgorithm::SHA256));
+    let digest = responder
+        .generate_response("J\u{e4}s\u{f8}n Doe", "Secret, or not?", "GET", "/", b"")
+        .expect("Could not generate response");
+
+    match digest.username {
+        Username::Encoded(ref value) => {
+            assert_eq!("J\u{e4}s\u{f8}n Doe".as_bytes(), &value.value[..])
+        }
+        Username::Plain(_) => panic!("Expected an encoded username"),
+    }
+    assert_eq!(
+        Ok(()),
+        digest.validate_using_password("GET", b"", "Secret, or not?".to_owned())
+    )
+}
+
+#[test]
+fn test_increment() {
+    let mut responder = ChallengeResponder::new(rfc7616_challenge(HashAlgorithm::SHA256));
+    let first = responder
+        .generate_response(USERNAME, PASSWORD, "GET", "/", b"")
+        .expect("Could not generate response");
+    let second = responder.increment().expect("Could not increment response");
+    let third = responder.increment().expect("Could not increment response");
+
+    assert_eq!(first.client_nonce, second.client_nonce);
+    assert_eq!(Some(NonceCount(2)), second.nonce_count);
+    assert_eq!(Some(NonceCount(3)), third.nonce_count);
+    assert!(first.response != second.response);
+    assert_eq!(Ok(()), second.validate_using_password("GET", b"", PASSWORD.to_owned()));
+    assert_eq!(Ok(()), third.validate_using_password("GET", b"", PASSWORD.to_owned()))
+}
+
+#[test]
+fn test_increment_sans_response() {
+    let mut responder = ChallengeResponder::new(rfc7616_challenge(HashAlgorithm::SHA256));
+
+    assert_eq!(Err(DigestError::NoPreviousResponse), responder.increment())
+}
+
+#[test]
+fn test_respond_to_stale_challenge() {
+    for algorithm in &[HashAlgorithm::SHA256, HashAlgorithm::SHA256Session] {
+        let mut responder = ChallengeResponder::new(rfc7616_challenge(algorithm.clone()));
+        let first = responder
+            .generate_response(USERNAME, PASSWORD, "GET", "/dir/index.html", b"")
+            .expect("Could not generate response");
+        responder.increment().expect("Could not increment response");
+        let challenge = generate_stale_challenge(&first, "new-nonce".to_owned(), algorithm.clone());
+        assert!(challenge.is_stale());
+
+        let retry = responder
+            .respond_to_stale_challenge(challenge.clone())
+            .expect("Could not respond to stale challenge");
+        assert_eq!("new-nonce", retry.nonce);
+        assert_eq!(first.opaque, retry.opaque);
+        assert_eq!(Some(NonceCount(1)), retry.nonce_count);
+        assert!(first.client_nonce != retry.client_nonce);
+        assert_eq!(Ok(()), retry.validate_using_password("GET", b"", PASSWORD.to_owned()));
+        assert_eq!(&challenge, responder.challenge());
+
+        let next = responder.increment().expect("Could not increment response");
+        assert_eq!(Some(NonceCount(2)), next.nonce_count);
+        assert_eq!(Ok(()), next.validate_using_password("GET", b"", PASSWORD.to_owned()))
+    }
+}
+
+#[test]
+fn test_respond_to_stale_challenge_requiring_credentials() {
+    let mut responder = ChallengeResponder::new(rfc7616_challenge(HashAlgorithm::SHA256));
+    let stale = rfc7616_challenge(HashAlgorithm::SHA256);
+    assert!(!stale.is_stale());
+    assert_eq!(
+        Err(DigestError::NoPreviousResponse),
+        responder.respond_to_stale_challenge(stale.clone())
+    );
+
+    let digest = responder
+        .generate_response(USERNAME, PASSWORD, "GET", "/", b"")
+        .expect("Could not generate response");
+    assert_eq!(
+        Err(DigestError::CredentialsRequired),
+        responder.respond_to_stale_challenge(stale)
+    );
+    let other_algorithm =
+        generate_stale_challenge(&digest, "new".to_owned(), HashAlgorithm::SHA512256);
+    assert_eq!(
+        Err(DigestError::CredentialsRequired),
+        responder.respond_to_stale_challenge(other_algorithm)
+    );
+    assert!(responder.increment().is_ok())
+}
+
+#[test]
+fn test_challenge_cache() {
+    let challenge = rfc7616_challenge(HashAlgorithm::SHA256);
+    let mut cache = ChallengeCache::new();
+    assert_eq!(None, cache.get("www.example.org", &challenge.realm));
+
+    cache.insert("www.example.org", challenge.clone(), Duration::from_secs(60));
+    assert_eq!(Some(&challenge), cache.get("www.example.org", &challenge.realm));
+    assert_eq!(None, cache.get("www.example.com", &challenge.realm));
+    assert_eq!(None, cache.get("www.example.org", "other@example.org"))
+}
+
+#[allow(deprecated)]
+#[test]
+fn test_challenge_cache_replaces_challenge() {
+    let mut cache = ChallengeCache::new();
+    let first = rfc7616_challenge(HashAlgorithm::MD5);
+    let second = rfc7616_challenge(HashAlgorithm::SHA256);
+    cache.insert("www.example.org", first, Duration::from_secs(60));
+    cache.insert("www.example.org", second.clone(), Duration::from_secs(60));
+
+    assert_eq!(Some(&second), cache.get("www.example.org", &second.realm))
+}
+
+#[test]
+fn test_challenge_cache_expiry() {
+    let challenge = rfc7616_challenge(HashAlgorithm::SHA256);
+    let mut cache = ChallengeCache::new();
+    cache.insert("www.example.org", challenge.clone(), Duration::from_secs(0));
+
+    assert_eq!(None, cache.get("www.example.org", &challenge.realm));
+    cache.purge_expired();
+    assert!(cache.challenges.is_empty());
+}
+
+#[test]
+fn test_challenge_cache_without_expiry() {
+    let challenge = rfc7616_challenge(HashAlgorithm::SHA256);
+    let mut cache = ChallengeCache::new();
+    cache.insert("www.example.org", challenge.clone(), Duration::MAX);
+    cache.purge_expired();
+
+    assert_eq!(Some(&challenge), cache.get("www.example.org", &challenge.realm))
+}
+
+#[test]
+fn test_challenge_cache_insert_removes_expired_challenges() {
+    let challenge = rfc7616_challenge(HashAlgorithm::SHA256);
+    let mut cache = ChallengeCache::new();
+    cache.insert("www.example.org", challenge.clone(), Duration::from_secs(0));
+    cache.insert("www.example.com", challenge.clone(), Duration::from_secs(60));
+
+    assert_eq!(1, cache.challenges.len());
+    assert_eq!(Some(&challenge), cache.get("www.example.com", &challenge.realm))
+}
+
+#[test]
+fn test_respond_to_401() {
+    // See: RFC 7616, Section 3.9.1, with both challenges in one header value
+    let value = "Digest realm=\"http-auth@example.org\", qop=\"auth, auth-int\", algorithm=MD5, \
+                 nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", \
+                 opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\", \
+                 Digest realm=\"http-auth@example.org\", qop=\"auth, auth-int\", \
+                 algorithm=SHA-256, nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", \
+                 opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\"";
+    let header = respond_to_401(value, USERNAME, PASSWORD, "GET", "/dir/index.html", b"")
+        .expect("Could not respond to challenge");
+    assert!(header.starts_with("Digest "));
+
+    let digest = parse_digest_header(&header).expect("Could not parse response");
+    assert_eq!(HashAlgorithm::SHA256, digest.algorithm);
+    assert_eq!("http-auth@example.org", digest.realm);
+    assert_eq!("7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v", digest.nonce);
+    assert_eq!("/dir/index.html", digest.request_uri);
+    assert_eq!(Some(Qop::Auth), digest.qop);
+    assert_eq!(Ok(()), digest.validate_using_password("GET", b"", PASSWORD.to_owned()));
+}
+
+#[test]
+fn test_respond_to_401_without_digest_challenge() {
+    assert_eq!(
+        Err(DigestError::MissingSchemePrefix),
+        respond_to_401("Basic realm=\"simple\"", USERNAME, PASSWORD, "GET", "/", b"")
+    );
+}
+
+#[allow(deprecated)]
+fn md5_and_sha256_challenges() -> Vec<Challenge> {
+    vec![
+        rfc7616_challenge(HashAlgorithm::MD5),
+        rfc7616_challenge(HashAlgorithm::SHA256),
+    ]
+}
+
+#[test]
+fn test_algorithm_preference_strongest() {
+    let challenges = md5_and_sha256_challenges();
+    let selected = AlgorithmPreference::Strongest.select(&challenges);
+    assert_eq!(Some(HashAlgorithm::SHA256), selected.map(|c| c.algorithm.clone()));
+    assert_eq!(AlgorithmPreference::Strongest, AlgorithmPreference::default());
+    assert_eq!(None, AlgorithmPreference::Strongest.select(&[]));
+}
+
+#[allow(deprecated)]
+#[test]
+fn test_algorithm_preference_weakest() {
+    let challenges = md5_and_sha256_challenges();
+    let selected = AlgorithmPreference::Weakest.select(&challenges);
+    assert_eq!(Some(HashAlgorithm::MD5), selected.map(|c| c.algorithm.clone()));
+}
+
+#[allow(deprecated)]
+#[test]
+fn test_algorithm_preference_preferred() {
+    let challenges = md5_and_sha256_challenges();
+    let preferred = |algorithms: Vec<HashAlgorithm>| {
+        AlgorithmPreference::Preferred(algorithms)
+            .select(&challenges)
+            .map(|c| c.algorithm.clone())
+    };
+    assert_eq!(
+        Some(HashAlgorithm::MD5),
+        preferred(vec![HashAlgorithm::MD5, HashAlgorithm::SHA256])
+    );
+    assert_eq!(
+        Some(HashAlgorithm::MD5),
+        preferred(vec![HashAlgorithm::SHA512256, HashAlgorithm::MD5])
+    );
+    // Falls back to the strongest algorithm
+    assert_eq!(Some(HashAlgorithm::SHA256), preferred(vec![HashAlgorithm::SHA512256]));
+    assert_eq!(Some(HashAlgorithm::SHA256), preferred(vec![]));
+}
+
+#[allow(deprecated)]
+#[test]
+fn test_with_algorithm_preference() {
+    let responder = ChallengeResponder::from_challenges(md5_and_sha256_challenges())
+        .expect("Could not create responder");
+    assert_eq!(HashAlgorithm::SHA256, responder.challenge().algorithm);
+
+    let mut responder = responder.with_algorithm_preference(AlgorithmPreference::Weakest);
+    assert_eq!(HashAlgorithm::MD5, responder.challenge().algorithm);
+    let digest = responder
+        .generate_response(USERNAME, PASSWORD, "GET", "/dir/index.html", b"")
+        .expect("Could not generate response");
+    assert_eq!(HashAlgorithm::MD5, digest.algorithm);
+
+    let responder = responder.with_algorithm_preference(
+        AlgorithmPreference::Preferred(vec![HashAlgorithm::SHA256]),
+    );
+    assert_eq!(HashAlgorithm::SHA256, responder.challenge().algorithm);
+
+    assert!(ChallengeResponder::from_challenges(vec![]).is_err());
+}
+
+#[allow(deprecated)]
+#[test]
+fn test_respond_to_401_with_algorithm_preference() {
+    let value = md5_and_sha256_challenges()
+        .iter()
+        .map(|challenge| challenge.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let header = respond_to_401_with_algorithm_preference(
+        &value,
+        USERNAME,
+        PASSWORD,
+        "GET",
+        "/dir/index.html",
+        b"",
+        AlgorithmPreference::Preferred(vec![HashAlgorithm::MD5]),
+    );
+    let header = header.expect("Could not respond to challenge");
+
+    let digest = parse_digest_header(&header).expect("Could not parse response");
+    assert_eq!(HashAlgorithm::MD5, digest.algorithm);
+    assert_eq!(Ok(()), digest.validate_using_password("GET", b"", PASSWORD.to_owned()));
+}
+
+#[test]
+fn test_generate_cnonce() {
+    let cnonce = generate_cnonce();
+    // 16 bytes encoded as unpadded base64url
+    assert_eq!(22, cnonce.len());
+    assert!(cnonce.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_'));
+    assert!(cnonce != generate_cnonce());
+}
+
+#[test]
+fn test_generate_cnonce_bytes() {
+    let mut first = [0u8; 32];
+    let mut second = [0u8; 32];
+    assert_eq!(Ok(()), generate_cnonce_bytes(&mut first));
+    assert_eq!(Ok(()), generate_cnonce_bytes(&mut second));
+    assert!(first != second);
+}
@@ -20,85 +20,71 @@
 
 //! Common authentication types.
 
-use crypto_hash;
 use hex::FromHex;
 use hyper::error::Error;
-use std::collections::HashMap;
 use std::fmt;
 use std::str::FromStr;
-use super::parsing::unraveled_map_value;
-use unicase::UniCase;
+use super::error::DigestError;
+use super::core::Hasher;
+use super::parsing::unraveled_param_value;
 
-/// Allowable hash algorithms for the `algorithm` parameter.
-#[derive(Clone, Debug, PartialEq)]
-pub enum HashAlgorithm {
-    /// `MD5`
-    MD5,
-    /// `MD5-sess`
-    MD5Session,
-    /// `SHA-256`
-    SHA256,
-    /// `SHA-256-sess`
-    SHA256Session,
-    /// `SHA-512-256`
-    SHA512256,
-    /// `SHA-512-256-sess`
-    SHA512256Session,
-}
+pub use super::core::{HashAlgorithm, Qop};
 
 impl FromStr for HashAlgorithm {
-    type Err = Error;
+    type Err = DigestError;
 
-    fn from_str(s: &str) -> Result<HashAlgorithm, Error> {
-        match s {
-            "MD5" => Ok(HashAlgorithm::MD5),
-            "MD5-sess" => Ok(HashAlgorithm::MD5Session),
-            "SHA-256" => Ok(HashAlgorithm::SHA256),
-            "SHA-256-sess" => Ok(HashAlgorithm::SHA256Session),
-            "SHA-512-256" => Ok(HashAlgorithm::SHA512256),
-            "SHA-512-256-sess" => Ok(HashAlgorithm::SHA512256Session),
-            _ => Err(Error::Header),
-        }
+    fn from_str(s: &str) -> Result<HashAlgorithm, DigestError> {
+        HashAlgorithm::from_name(s).ok_or_else(|| DigestError::InvalidAlgorithm(s.to_owned()))
     }
 }
 
-impl fmt::Display for HashAlgorithm {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
-            HashAlgorithm::MD5 => write!(f, "{}", "MD5"),
-            HashAlgorithm::MD5Session => write!(f, "{}", "MD5-sess"),
-            HashAlgorithm::SHA256 => write!(f, "{}", "SHA-256"),
-            HashAlgorithm::SHA256Session => write!(f, "{}", "SHA-256-sess"),
-            HashAlgorithm::SHA512256 => write!(f, "{}", "SHA-512-256"),
-            HashAlgorithm::SHA512256Session => write!(f, "{}", "SHA-512-256-sess"),
-        }
-    }
+/// Incrementally hashes an entity body, for use with `qop=auth-int` when the body is too large
+/// to hold in memory.
+///
+/// The result of `finalize` is the same as `HashAlgorithm::hex_digest` over the concatenated
+/// chunks, and can be passed to `Digest::using_hashed_a1_and_body_hash`.
+pub struct DigestBodyHasher {
+    hasher: Box<dyn Hasher>,
 }
 
-impl HashAlgorithm {
-    fn to_algorithm(&self) -> crypto_hash::Algorithm {
-        match *self {
-            HashAlgorithm::MD5 |
-            HashAlgorithm::MD5Session => crypto_hash::Algorithm::MD5,
-            HashAlgorithm::SHA256 |
-            HashAlgorithm::SHA256Session => crypto_hash::Algorithm::SHA256,
-            HashAlgorithm::SHA512256 |
-            HashAlgorithm::SHA512256Session => crypto_hash::Algorithm::SHA512,
-        }
+impl DigestBodyHasher {
+    /// Creates a hasher for the given algorithm.
+    pub fn new(algorithm: &HashAlgorithm) -> DigestBodyHasher {
+        DigestBodyHasher { hasher: algorithm.hasher() }
     }
 
-    /// Generate a hexadecimal representation of the output of a cryptographic hash function, given
-    /// `data` and the algorithm.
-    pub fn hex_digest(&self, data: &[u8]) -> String {
-        let mut digest = crypto_hash::hex_digest(self.to_algorithm(), data);
-        if *self == HashAlgorithm::SHA512256 || *self == HashAlgorithm::SHA256Session {
-            digest.truncate(64);
-        }
+    /// Adds a chunk of the entity body to the hash.
+    pub fn update(&mut self, chunk: &[u8]) {
+        self.hasher.update(chunk);
+    }
 
-        digest
+    /// Consumes the hasher and returns the hexadecimal digest of the entity body.
+    pub fn finalize(self) -> String {
+        self.hasher.finalize()
+    }
+}
+
+/// Decodes the 8 hexadecimal digits of an `nc` parameter into a nonce count.
+pub fn parse_nonce_count(hex: &str) -> Result<u32, DigestError> {
+    match Vec::from_hex(hex) {
+        Ok(ref bytes) if bytes.len() == 4 => {
+            let mut count: u32 = 0;
+            count |= (bytes[0] as u32) << 24;
+            count |= (bytes[1] as u32) << 16;
+            count |= (bytes[2] as u32) << 8;
+            count |= bytes[3] as u32;
+            Ok(count)
+        }
+        _ => Err(DigestError::MalformedNonceCount(hex.to_owned())),
     }
 }
 
+/// Encodes a nonce count as the 8 zero-padded, lowercase hexadecimal digits of an `nc` parameter.
+/// This is the inverse of `parse_nonce_count`.
+pub fn format_nonce_count(nc: u32) -> String {
+    format!("{:08x}", nc)
+}
+
 /// Convenience type for nonce counts.
 #[derive(Clone, Debug, PartialEq)]
 pub struct NonceCount(pub u32);
@@ -106,34 +92,24 @@ pub struct NonceCount(pub u32);
 impl FromStr for NonceCount {
     type Err = Error;
     fn from_str(s: &str) -> Result<NonceCount, Error> {
-        match Vec::from_hex(s) {
-            Ok(bytes) => {
-                let mut count: u32 = 0;
-                count |= (bytes[0] as u32) << 24;
-                count |= (bytes[1] as u32) << 16;
-                count |= (bytes[2] as u32) << 8;
-                count |= bytes[3] as u32;
-                Ok(NonceCount(count))
-            }
-            _ => Err(Error::Header),
-        }
+        parse_nonce_count(s).map(NonceCount).map_err(|_| Error::Header)
     }
 }
 
 impl fmt::Display for NonceCount {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let NonceCount(value) = *self;
-        write!(f, "{:08x}", value)
+        f.write_str(&format_nonce_count(value))
     }
 }
 
 impl NonceCount {
-    /// Extracts an `NonceCount` object from a map of header parameters.
+    /// Extracts an `NonceCount` object from a list of header parameters.
     /// Returns an error if the value is not a valid nonce count.
     pub fn from_parameters(
-        map: &HashMap<UniCase<String>, String>,
+        params: &[(&str, &str)],
     ) -> Result<Option<NonceCount>, Error> {
-        if let Some(value) = unraveled_map_value(map, "nc") {
+        if let Some(value) = unraveled_param_value(params, "nc") {
             match NonceCount::from_str(&value[..]) {
                 Ok(count) => Ok(Some(count)),
                 _ => Err(Error::Header),
@@ -144,15 +120,6 @@ impl NonceCount {
     }
 }
 
-/// Allowable values for the `qop`, or "quality of protection" parameter.
-#[derive(Clone, Debug, PartialEq)]
-pub enum Qop {
-    /// `auth`
-    Auth,
-    /// `auth-int`
-    AuthInt,
-}
-
 impl FromStr for Qop {
     type Err = Error;
     fn from_str(s: &str) -> Result<Qop, Error> {
@@ -164,20 +131,11 @@ impl FromStr for Qop {
     }
 }
 
-impl fmt::Display for Qop {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
-            Qop::Auth => write!(f, "{}", "auth"),
-            Qop::AuthInt => write!(f, "{}", "auth-int"),
-        }
-    }
-}
-
 impl Qop {
-    /// Extracts a `Qop` object from a map of header parameters.
+    /// Extracts a `Qop` object from a list of header parameters.
     /// Returns an error if the value is not a valid qop value.
-    pub fn from_parameters(map: &HashMap<UniCase<String>, String>) -> Result<Option<Qop>, Error> {
-        if let Some(value) = unraveled_map_value(map, "qop") {
+    pub fn from_parameters(params: &[(&str, &str)]) -> Result<Option<Qop>, Error> {
+        if let Some(value) = unraveled_param_value(params, "qop") {
             match Qop::from_str(&value[..]) {
                 Ok(converted) => Ok(Some(converted)),
                 Err(_) => Err(Error::Header),
@@ -186,4 +144,15 @@ impl Qop {
             Ok(None)
         }
     }
+
+    /// Parses a comma-delimited list of `qop` values, as sent by servers in a `WWW-Authenticate`
+    /// challenge (e.g. `auth, auth-int`). Clients send a single value, which `from_str` parses.
+    pub fn parse_list(s: &str) -> Result<Vec<Qop>, DigestError> {
+        s.split(',')
+            .map(str::trim)
+            .map(|token| {
+                Qop::from_str(token).map_err(|_| DigestError::InvalidQop(token.to_owned()))
+            })
+            .collect()
+    }
 }
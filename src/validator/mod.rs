@@ -0,0 +1,648 @@
+// Copyright (c) 2017 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Server-side validation of `Digest` authorization headers.
+
+use base64::Engine;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+#[cfg(feature = "http")]
+use http::{Request, Response, StatusCode};
+#[cfg(feature = "http")]
+use http::header::WWW_AUTHENTICATE;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+use super::challenge::{Challenge, ChallengeBuilder};
+use super::digest::{Digest, log_validation_result, uri_in_domain, validate_request_uri};
+use super::error::{DigestError, ValidationFailure};
+use super::types::{HashAlgorithm, Qop};
+
+mod test;
+
+/// The result of validating a `Digest`.
+pub type ValidationResult = Result<(), ValidationFailure>;
+
+/// Validates a `Digest`, given an HTTP request and a function which looks up the hexadecimal
+/// digest of the A1 string for a username and realm (e.g., from an `htdigest` file).
+///
+/// Encoded usernames are passed to `lookup` as (lossily decoded) UTF-8. Anonymous `Digest`s (see
+/// `Digest::is_anonymous`) are looked up with an empty username, so servers which allow anonymous
+/// access must return credentials for it; otherwise validation fails with `AnonymousNotAllowed`.
+///
+/// `entity_body` is defined in
+/// [RFC 2616, secion 7.2](https://tools.ietf.org/html/rfc2616#section-7.2).
+pub fn validate_digest_using_ha1_fn<F>(
+    digest: &Digest,
+    method: &str,
+    entity_body: &[u8],
+    lookup: F,
+) -> ValidationResult
+where
+    F: Fn(&str, &str) -> Option<String>,
+{
+    let result = digest.validate_response_with(|| {
+        let username = String::from_utf8_lossy(digest.username.as_bytes());
+        match lookup(&username, &digest.realm) {
+            Some(a1) => {
+                digest
+                    .using_hashed_a1(method, entity_body, a1)
+                    .map_err(ValidationFailure::MalformedDigest)
+            }
+            None if digest.is_anonymous() => Err(ValidationFailure::AnonymousNotAllowed),
+            None => Err(ValidationFailure::UnknownUsername),
+        }
+    });
+    log_validation_result(digest, &result);
+    result
+}
+
+/// Looks up the credentials used to validate `Digest`s.
+pub trait CredentialStore {
+    /// Returns the hexadecimal digest of the A1 string for a username and realm, or `None` if
+    /// the user is unknown.
+    ///
+    /// Encoded usernames are passed as (lossily decoded) UTF-8.
+    fn lookup_a1(&self, username: &str, realm: &str) -> Option<String>;
+}
+
+/// A `CredentialStore` which keeps its credentials in memory.
+///
+/// `Debug` only shows the usernames and realms, not the A1 digests.
+#[derive(Clone, Default)]
+pub struct InMemoryCredentialStore {
+    credentials: HashMap<(String, String), String>,
+}
+
+impl fmt::Debug for InMemoryCredentialStore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let users: Vec<&(String, String)> = self.credentials.keys().collect();
+        f.debug_struct("InMemoryCredentialStore").field("users", &users).finish_non_exhaustive()
+    }
+}
+
+impl InMemoryCredentialStore {
+    /// Creates an empty credential store.
+    pub fn new() -> InMemoryCredentialStore {
+        InMemoryCredentialStore::default()
+    }
+
+    /// Adds a user, given the hexadecimal digest of their A1 string (e.g., from an `htdigest`
+    /// file).
+    pub fn insert_hashed_a1(&mut self, username: String, realm: String, a1: String) {
+        self.credentials.insert((username, realm), a1);
+    }
+
+    /// Adds a user, given their password. The A1 string is hashed with `algorithm`, which must be
+    /// the algorithm that clients use to generate their `Digest`s.
+    pub fn insert_password(
+        &mut self,
+        algorithm: &HashAlgorithm,
+        username: String,
+        realm: String,
+        password: &str,
+    ) {
+        let a1 = Digest::simple_hashed_a1(algorithm, &username, &realm, password);
+        self.insert_hashed_a1(username, realm, a1);
+    }
+}
+
+impl CredentialStore for InMemoryCredentialStore {
+    fn lookup_a1(&self, username: &str, realm: &str) -> Option<String> {
+        self.credentials
+            .get(&(username.to_owned(), realm.to_owned()))
+            .cloned()
+    }
+}
+
+struct RealmRoute {
+    prefix: String,
+    realm: String,
+    store: Box<dyn CredentialStore>,
+}
+
+/// Maps path prefixes to realms and their `CredentialStore`s, for servers which host multiple
+/// realms, e.g. `/api` and `/admin`.
+///
+/// Prefixes match whole path segments, so `/api` matches `/api` and `/api/v1`, but not
+/// `/apiary`. Paths are compared like request URIs, so dot segments are resolved first and
+/// `/api/../admin` is routed to `/admin`. When several prefixes match, the longest one wins.
+#[derive(Default)]
+pub struct RealmRouter {
+    routes: Vec<RealmRoute>,
+}
+
+impl RealmRouter {
+    /// Creates a router without any realms.
+    pub fn new() -> RealmRouter {
+        RealmRouter::default()
+    }
+
+    /// Protects the paths starting with `prefix` with `realm`, whose credentials are looked up in
+    /// `store`. Replaces the realm previously added for the same prefix, if any.
+    pub fn add_realm(&mut self, prefix: &str, realm: String, store: Box<dyn CredentialStore>) {
+        self.routes.retain(|route| route.prefix != prefix);
+        self.routes.push(RealmRoute {
+            prefix: prefix.to_owned(),
+            realm,
+            store,
+        });
+    }
+
+    fn find_route(&self, path: &str) -> Option<&RealmRoute> {
+        self.routes
+            .iter()
+            .filter(|route| uri_in_domain(path, &route.prefix))
+            .max_by_key(|route| route.prefix.len())
+    }
+
+    /// Returns the credential store for the realm which protects `path`, or `None` if `path` is
+    /// not protected, or if `digest` was generated for a different realm.
+    pub fn route(&self, path: &str, digest: &Digest) -> Option<&dyn CredentialStore> {
+        self.find_route(path)
+            .filter(|route| route.realm == digest.realm)
+            .map(|route| route.store.as_ref())
+    }
+
+    /// Generates a challenge for the realm which protects `path`, using SHA-256 and the `auth`
+    /// qop, or `None` if `path` is not protected.
+    pub fn generate_challenge(&self, path: &str, nonce: String) -> Option<Challenge> {
+        self.find_route(path).map(|route| {
+            Challenge {
+                realm: route.realm.clone(),
+                domain: vec![route.prefix.clone()],
+                nonce,
+                opaque: None,
+                stale: false,
+                algorithm: HashAlgorithm::SHA256,
+                qop: vec![Qop::Auth],
+                charset: None,
+                userhash: false,
+            }
+        })
+    }
+}
+
+/// Tracks the nonces issued by a server, and the nonce counts used with them, to protect against
+/// replay attacks.
+pub trait NonceStore {
+    /// Records that `nonce` was issued in a challenge.
+    fn insert_nonce(&mut self, nonce: &str);
+
+    /// Records that a `Digest` used `nc` with `nonce`. Returns an error if `nonce` was not
+    /// issued, or if the nonce count must be rejected, in which case nothing is recorded.
+    fn record_use(&mut self, nonce: &str, nc: u32) -> ValidationResult;
+
+    /// Returns the highest nonce count used with `nonce`, or `None` if `nonce` is unknown or has
+    /// not been used yet. The next nonce count expected from a client is one higher.
+    fn max_nc_seen(&self, nonce: &str) -> Option<u32>;
+}
+
+#[derive(Clone, Debug)]
+struct NonceUses {
+    issued_at: u64,
+    seen: HashSet<u32>,
+    max: Option<u32>,
+}
+
+pub(crate) fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// A `NonceStore` which keeps its nonces in memory.
+///
+/// By default, only nonce counts which were already used are rejected, since clients may send
+/// concurrent requests which arrive out of order.
+///
+/// Nonces are kept until they are evicted, e.g. via `evict_older_than`. Evicted nonces are
+/// remembered until `forget_expired_nonces` is called, so that their use is rejected as expired
+/// rather than unknown.
+pub struct InMemoryNonceStore {
+    nonces: HashMap<String, NonceUses>,
+    expired: HashSet<String>,
+    strict_ordering: bool,
+    clock: Box<dyn Fn() -> u64 + Send + Sync>,
+}
+
+impl Default for InMemoryNonceStore {
+    fn default() -> InMemoryNonceStore {
+        InMemoryNonceStore::with_clock(Box::new(unix_timestamp))
+    }
+}
+
+impl fmt::Debug for InMemoryNonceStore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("InMemoryNonceStore")
+            .field("nonces", &self.nonces)
+            .field("expired", &self.expired)
+            .field("strict_ordering", &self.strict_ordering)
+            .finish()
+    }
+}
+
+impl InMemoryNonceStore {
+    /// Creates an empty nonce store, which timestamps nonces with the number of seconds since the
+    /// Unix epoch.
+    pub fn new() -> InMemoryNonceStore {
+        InMemoryNonceStore::default()
+    }
+
+    /// Creates an empty nonce store, which timestamps nonces with `clock` when they are inserted.
+    pub fn with_clock(clock: Box<dyn Fn() -> u64 + Send + Sync>) -> InMemoryNonceStore {
+        InMemoryNonceStore {
+            nonces: HashMap::new(),
+            expired: HashSet::new(),
+            strict_ordering: false,
+            clock,
+        }
+    }
+
+    /// Sets whether each nonce count must be exactly one higher than the highest nonce count used
+    /// with the nonce so far (starting at 1), as recommended by
+    /// [RFC 7616, section 3.4](https://tools.ietf.org/html/rfc7616#section-3.4).
+    pub fn set_strict_ordering(&mut self, strict_ordering: bool) {
+        self.strict_ordering = strict_ordering;
+    }
+
+    /// Removes all nonces which were inserted before `cutoff`, after which their use is rejected
+    /// as `ValidationFailure::NonceExpired`.
+    pub fn evict_before_timestamp(&mut self, cutoff: u64) {
+        let expired = &mut self.expired;
+        self.nonces.retain(|nonce, uses| {
+            if uses.issued_at < cutoff {
+                expired.insert(nonce.clone());
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    /// Removes all nonces which are more than `max_age_secs` old at the timestamp `now`.
+    pub fn evict_older_than(&mut self, max_age_secs: u64, now: u64) {
+        self.evict_before_timestamp(now.saturating_sub(max_age_secs));
+    }
+
+    /// Forgets all evicted nonces, after which their use is rejected as
+    /// `ValidationFailure::UnknownNonce`.
+    pub fn forget_expired_nonces(&mut self) {
+        self.expired.clear();
+    }
+}
+
+impl NonceStore for InMemoryNonceStore {
+    fn insert_nonce(&mut self, nonce: &str) {
+        let issued_at = (self.clock)();
+        self.expired.remove(nonce);
+        self.nonces.entry(nonce.to_owned()).or_insert(NonceUses {
+            issued_at,
+            seen: HashSet::new(),
+            max: None,
+        });
+    }
+
+    fn record_use(&mut self, nonce: &str, nc: u32) -> ValidationResult {
+        let uses = match self.nonces.get_mut(nonce) {
+            Some(uses) => uses,
+            None if self.expired.contains(nonce) => return Err(ValidationFailure::NonceExpired),
+            None => return Err(ValidationFailure::UnknownNonce),
+        };
+        if self.strict_ordering {
+            let expected = uses.max.map_or(1, |max| max.wrapping_add(1));
+            if nc != expected {
+                return Err(ValidationFailure::NonceCountOutOfOrder { expected, got: nc });
+            }
+        } else if uses.seen.contains(&nc) {
+            return Err(ValidationFailure::NonceCountReused);
+        }
+        uses.seen.insert(nc);
+        uses.max = uses.max.max(Some(nc));
+        Ok(())
+    }
+
+    fn max_nc_seen(&self, nonce: &str) -> Option<u32> {
+        self.nonces.get(nonce).and_then(|uses| uses.max)
+    }
+}
+
+/// Validates a `Digest`, given an `http::Request` and a password.
+///
+/// The method, URI, and entity body are taken from `request`. The `Digest` is only valid if its
+/// `uri` matches the request URI (see `validate_request_uri`); otherwise,
+/// `ValidationFailure::StructureInvalid` is returned with `DigestError::UriMismatch`.
+#[cfg(feature = "http")]
+pub fn validate_digest_for_request<B: AsRef<[u8]>>(
+    digest: &Digest,
+    request: &Request<B>,
+    password: &str,
+) -> Result<(), ValidationFailure> {
+    match check_request_uri(digest, request) {
+        Ok(()) => {
+            digest.validate_using_password(
+                request.method().as_str(),
+                request.body().as_ref(),
+                password.to_owned(),
+            )
+        }
+        Err(failure) => {
+            let result = Err(failure);
+            log_validation_result(digest, &result);
+            result
+        }
+    }
+}
+
+/// Checks that the `uri` of `digest` matches the request URI.
+#[cfg(feature = "http")]
+fn check_request_uri<B>(digest: &Digest, request: &Request<B>) -> Result<(), ValidationFailure> {
+    validate_request_uri(&digest.request_uri, &request.uri().to_string())
+        .map_err(ValidationFailure::StructureInvalid)
+}
+
+/// A request to validate via `validate_batch`: the `Digest` from its `Authorization` header, its
+/// method, its entity body, and its Request-URI.
+pub type BatchRequest<'a> = (Digest, &'a str, &'a [u8], String);
+
+/// Validates the `Digest`s of a batch of requests against the same password, e.g. in an API
+/// gateway which checks many requests for one service account at once. Each `Digest` is only valid
+/// if its `uri` matches the request's Request-URI (see `validate_request_uri`).
+///
+/// Returns the result for each request, in the same order as `requests`.
+pub fn validate_batch(
+    requests: &[BatchRequest],
+    password: &str,
+) -> Vec<Result<(), ValidationFailure>> {
+    requests
+        .iter()
+        .map(|request| validate_batch_request(request, password))
+        .collect()
+}
+
+/// Validates a batch of requests like `validate_batch`, with the requests divided among the
+/// threads of the global [rayon](https://crates.io/crates/rayon) thread pool.
+#[cfg(feature = "rayon")]
+pub fn validate_batch_parallel(
+    requests: &[BatchRequest],
+    password: &str,
+) -> Vec<Result<(), ValidationFailure>> {
+    requests
+        .par_iter()
+        .map(|request| validate_batch_request(request, password))
+        .collect()
+}
+
+fn validate_batch_request(request: &BatchRequest, password: &str) -> Result<(), ValidationFailure> {
+    let (ref digest, method, entity_body, ref request_uri) = *request;
+    if let Err(err) = validate_request_uri(&digest.request_uri, request_uri) {
+        let result = Err(ValidationFailure::StructureInvalid(err));
+        log_validation_result(digest, &result);
+        return result;
+    }
+    digest.validate_using_password(method, entity_body, password.to_owned())
+}
+
+/// Checks that `digest` returns the `opaque` parameter of the `challenge` it answers unchanged, as
+/// required by [RFC 7616, section 3.3](https://tools.ietf.org/html/rfc7616#section-3.3). If the
+/// challenge had no `opaque` parameter, the `Digest` must not have one either.
+pub fn validate_opaque(challenge: &Challenge, digest: &Digest) -> Result<(), DigestError> {
+    if challenge.opaque == digest.opaque {
+        Ok(())
+    } else {
+        Err(DigestError::OpaqueMismatch {
+            expected: challenge.opaque.clone(),
+            got: digest.opaque.clone(),
+        })
+    }
+}
+
+/// Checks that the Request-URI of `digest` is in the protection space of the `challenge` it
+/// answers (see `Challenge::covers_uri`), so that a `Digest` for a challenge whose domain is `/api`
+/// is not accepted for `/admin`.
+pub fn validate_domain(challenge: &Challenge, digest: &Digest) -> Result<(), DigestError> {
+    if challenge.covers_uri(&digest.request_uri) {
+        Ok(())
+    } else {
+        Err(DigestError::UriOutsideDomain(digest.request_uri.clone()))
+    }
+}
+
+/// Checks that a client nonce has not been used before with the current server nonce, to protect
+/// against replay attacks. Returns `true` and records `cnonce` in `seen` if it is new, and `false`
+/// otherwise.
+///
+/// `seen` grows without bound, so it must be cleared when the corresponding server nonce expires.
+pub fn validate_client_nonce_uniqueness(cnonce: &str, seen: &mut HashSet<String>) -> bool {
+    if seen.contains(cnonce) {
+        false
+    } else {
+        seen.insert(cnonce.to_owned())
+    }
+}
+
+/// Generates a nonce from a server secret `key` and a `timestamp`, e.g. the number of seconds since
+/// the Unix epoch, as suggested by
+/// [RFC 7616, section 3.3](https://tools.ietf.org/html/rfc7616#section-3.3).
+///
+/// The nonce is the hexadecimal timestamp followed by the SHA-256 digest of the timestamp and the
+/// key, so a server can recompute it to check that it issued the nonce and how old it is.
+pub fn generate_server_nonce(key: &[u8], timestamp: u64) -> String {
+    let timestamp = format!("{:016x}", timestamp);
+    let mut data = Vec::with_capacity(timestamp.len() + key.len() + 1);
+    data.extend_from_slice(timestamp.as_bytes());
+    data.push(b':');
+    data.extend_from_slice(key);
+    format!("{}{}", timestamp, HashAlgorithm::SHA256.hex_digest(&data))
+}
+
+/// Encodes random bytes as a nonce using standard, padded base64, like the nonces in the examples
+/// of [RFC 7616, section 3.9](https://tools.ietf.org/html/rfc7616#section-3.9).
+pub fn encode_nonce(bytes: &[u8]) -> String {
+    STANDARD.encode(bytes)
+}
+
+/// Decodes a nonce encoded by `encode_nonce`.
+pub fn decode_nonce(s: &str) -> Result<Vec<u8>, DigestError> {
+    STANDARD.decode(s).map_err(|_| DigestError::InvalidNonceEncoding)
+}
+
+/// Encodes random bytes as a nonce using unpadded base64url, so that the nonce only contains token
+/// characters.
+pub fn encode_nonce_url_safe(bytes: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+fn generate_401_challenge(
+    realm: &str,
+    key: &[u8],
+    timestamp: u64,
+    algorithm: HashAlgorithm,
+    qop: Vec<Qop>,
+) -> Result<Challenge, DigestError> {
+    ChallengeBuilder::new(realm, generate_server_nonce(key, timestamp))
+        .algorithm(algorithm)
+        .qop_options(qop)
+        .build()
+}
+
+/// Generates the `WWW-Authenticate` header of a `401 Unauthorized` response, as a (name, value)
+/// pair, with a challenge for `realm` whose nonce is generated by `generate_server_nonce`.
+///
+/// Returns an error if the realm contains a carriage return or line feed.
+pub fn generate_401_response(
+    realm: &str,
+    key: &[u8],
+    timestamp: u64,
+    algorithm: HashAlgorithm,
+    qop: Vec<Qop>,
+) -> Result<(String, String), DigestError> {
+    let challenge = generate_401_challenge(realm, key, timestamp, algorithm, qop)?;
+    Ok(("WWW-Authenticate".to_owned(), challenge.to_string()))
+}
+
+/// Generates a `401 Unauthorized` `http::Response` with the `WWW-Authenticate` header generated
+/// by `generate_401_response`.
+#[cfg(feature = "http")]
+pub fn generate_401_http_response(
+    realm: &str,
+    key: &[u8],
+    timestamp: u64,
+    algorithm: HashAlgorithm,
+    qop: Vec<Qop>,
+) -> Result<Response<()>, DigestError> {
+    let challenge = generate_401_challenge(realm, key, timestamp, algorithm, qop)?;
+    let mut response = Response::new(());
+    *response.status_mut() = StatusCode::UNAUTHORIZED;
+    response.headers_mut().insert(
+        WWW_AUTHENTICATE,
+        challenge.to_www_authenticate_header_value()?,
+    );
+    Ok(response)
+}
+
+/// Receives authentication events from a `DigestAuthValidator`, e.g. for audit logging.
+///
+/// Loggers must be `Send + Sync`, so that a validator can be shared between threads, e.g. as
+/// axum or tower state.
+pub trait DigestAuthLogger: Send + Sync {
+    /// Called when a `Digest` is successfully validated.
+    fn on_success(&self, username: &str, realm: &str, uri: &str, algorithm: &HashAlgorithm);
+    /// Called when a `Digest` fails validation.
+    fn on_failure(&self, reason: &ValidationFailure, realm: &str);
+    /// Called when the server rejects a `Digest` because its nonce is stale.
+    fn on_stale_nonce(&self, nonce: &str);
+}
+
+/// A `DigestAuthLogger` that ignores all events.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoopLogger;
+
+impl DigestAuthLogger for NoopLogger {
+    fn on_success(&self, _: &str, _: &str, _: &str, _: &HashAlgorithm) {}
+    fn on_failure(&self, _: &ValidationFailure, _: &str) {}
+    fn on_stale_nonce(&self, _: &str) {}
+}
+
+/// Validates `Digest` authorization headers for a single realm, reporting the results to a
+/// `DigestAuthLogger`.
+pub struct DigestAuthValidator {
+    realm: String,
+    logger: Box<dyn DigestAuthLogger>,
+}
+
+impl DigestAuthValidator {
+    /// Creates a validator for `realm` which does not log authentication events.
+    pub fn new(realm: String) -> DigestAuthValidator {
+        DigestAuthValidator::with_logger(realm, Box::new(NoopLogger))
+    }
+
+    /// Creates a validator for `realm` which reports authentication events to `logger`.
+    pub fn with_logger(realm: String, logger: Box<dyn DigestAuthLogger>) -> DigestAuthValidator {
+        DigestAuthValidator { realm, logger }
+    }
+
+    /// The realm that `Digest`s are validated against.
+    pub fn realm(&self) -> &str {
+        &self.realm
+    }
+
+    fn check_and_log_response<F>(&self, digest: &Digest, expected: F) -> ValidationResult
+    where
+        F: FnOnce() -> Result<String, DigestError>,
+    {
+        let result = if digest.realm != self.realm {
+            Err(ValidationFailure::RealmMismatch)
+        } else {
+            digest.validate_response_with(|| expected().map_err(ValidationFailure::MalformedDigest))
+        };
+
+        log_validation_result(digest, &result);
+        match result {
+            Ok(()) => {
+                let username = String::from_utf8_lossy(digest.username.as_bytes());
+                self.logger.on_success(
+                    &username,
+                    &digest.realm,
+                    &digest.request_uri,
+                    &digest.algorithm,
+                )
+            }
+            Err(ref reason) => self.logger.on_failure(reason, &self.realm),
+        }
+
+        result
+    }
+
+    /// Validates a `Digest`, given an HTTP request and a password.
+    ///
+    /// `entity_body` is defined in
+    /// [RFC 2616, secion 7.2](https://tools.ietf.org/html/rfc2616#section-7.2).
+    pub fn validate_using_password(
+        &self,
+        digest: &Digest,
+        method: &str,
+        entity_body: &[u8],
+        password: String,
+    ) -> ValidationResult {
+        self.check_and_log_response(digest, || digest.using_password(method, entity_body, password))
+    }
+
+    /// Validates a `Digest`, given an HTTP request and a hexadecimal digest of an A1 string.
+    ///
+    /// `entity_body` is defined in
+    /// [RFC 2616, secion 7.2](https://tools.ietf.org/html/rfc2616#section-7.2).
+    pub fn validate_using_hashed_a1(
+        &self,
+        digest: &Digest,
+        method: &str,
+        entity_body: &[u8],
+        a1: String,
+    ) -> ValidationResult {
+        self.check_and_log_response(digest, || digest.using_hashed_a1(method, entity_body, a1))
+    }
+
+    /// Reports that the server rejected `digest` because its nonce is stale.
+    pub fn reject_stale_nonce(&self, digest: &Digest) {
+        self.logger.on_stale_nonce(&digest.nonce)
+    }
+}
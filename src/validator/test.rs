@@ -0,0 +1,750 @@
+// Copyright (c) 2017 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+#![cfg(test)]
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use super::{BatchRequest, CredentialStore, DigestAuthLogger, DigestAuthValidator,
+            InMemoryCredentialStore, InMemoryNonceStore, NonceStore, RealmRouter, decode_nonce,
+            encode_nonce, encode_nonce_url_safe, generate_401_response, generate_server_nonce,
+            validate_batch, validate_client_nonce_uniqueness, validate_digest_using_ha1_fn,
+            validate_domain, validate_opaque};
+use super::super::challenge::Challenge;
+use super::super::challenge::test_helper::rfc7616_challenge;
+use super::super::error::{DigestError, ValidationFailure};
+use super::super::digest::{Digest, Username};
+use super::super::digest::test_helper::{rfc2617_digest_header, rfc7616_digest_header};
+use super::super::types::{HashAlgorithm, Qop};
+#[cfg(feature = "http")]
+use http::Request;
+#[cfg(feature = "http")]
+use super::{generate_401_http_response, validate_digest_for_request};
+#[cfg(feature = "rayon")]
+use super::validate_batch_parallel;
+
+#[derive(Clone, Default)]
+struct RecordingLogger {
+    events: Arc<Mutex<Vec<String>>>,
+}
+
+impl DigestAuthLogger for RecordingLogger {
+    fn on_success(&self, username: &str, realm: &str, uri: &str, algorithm: &HashAlgorithm) {
+        self.events.lock().unwrap().push(format!(
+            "success {} {} {} {}",
+            username,
+            realm,
+            uri,
+            algorithm
+        ));
+    }
+
+    fn on_failure(&self, reason: &ValidationFailure, realm: &str) {
+        self.events.lock().unwrap().push(
+            format!("failure {:?} {}", reason, realm),
+        );
+    }
+
+    fn on_stale_nonce(&self, nonce: &str) {
+        self.events.lock().unwrap().push(format!("stale {}", nonce));
+    }
+}
+
+fn validator_with_logger(realm: &str) -> (DigestAuthValidator, Arc<Mutex<Vec<String>>>) {
+    let logger = RecordingLogger::default();
+    let events = logger.events.clone();
+    (
+        DigestAuthValidator::with_logger(realm.to_owned(), Box::new(logger)),
+        events,
+    )
+}
+
+#[test]
+fn test_validator_is_send_and_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<DigestAuthValidator>();
+}
+
+#[allow(deprecated)]
+#[test]
+fn test_validate_using_password_with_default_logger() {
+    let validator = DigestAuthValidator::new("testrealm@host.com".to_owned());
+    let digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    let result =
+        validator.validate_using_password(&digest, "GET", b"", "Circle Of Life".to_owned());
+    assert_eq!(Ok(()), result)
+}
+
+#[test]
+fn test_validate_using_password_logs_success() {
+    let (validator, events) = validator_with_logger("http-auth@example.org");
+    let digest = rfc7616_digest_header(
+        HashAlgorithm::SHA256,
+        "753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1",
+    );
+    let result =
+        validator.validate_using_password(&digest, "GET", b"", "Circle of Life".to_owned());
+    assert_eq!(Ok(()), result);
+    assert_eq!(
+        vec![
+            "success Mufasa http-auth@example.org /dir/index.html SHA-256".to_owned(),
+        ],
+        *events.lock().unwrap()
+    );
+}
+
+#[allow(deprecated)]
+#[test]
+fn test_validate_using_password_logs_response_mismatch() {
+    let (validator, events) = validator_with_logger("testrealm@host.com");
+    let digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    let result = validator.validate_using_password(&digest, "GET", b"", "wrong".to_owned());
+    assert_eq!(Err(ValidationFailure::ResponseMismatch), result);
+    assert_eq!(
+        vec!["failure ResponseMismatch testrealm@host.com".to_owned()],
+        *events.lock().unwrap()
+    );
+}
+
+#[allow(deprecated)]
+#[test]
+fn test_validate_using_password_logs_realm_mismatch() {
+    let (validator, events) = validator_with_logger("other@host.com");
+    let digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    let result =
+        validator.validate_using_password(&digest, "GET", b"", "Circle Of Life".to_owned());
+    assert_eq!(Err(ValidationFailure::RealmMismatch), result);
+    assert_eq!(
+        vec!["failure RealmMismatch other@host.com".to_owned()],
+        *events.lock().unwrap()
+    );
+}
+
+#[allow(deprecated)]
+#[test]
+fn test_validate_using_hashed_a1_logs_invalid_structure() {
+    let (validator, events) = validator_with_logger("testrealm@host.com");
+    let mut digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    digest.client_nonce = None;
+    let result = validator.validate_using_hashed_a1(
+        &digest,
+        "GET",
+        b"",
+        "939e7578ed9e3c518a452acee763bce9".to_owned(),
+    );
+    assert_eq!(Err(ValidationFailure::StructureInvalid(DigestError::MissingClientNonce)), result);
+    assert_eq!(
+        vec!["failure StructureInvalid(MissingClientNonce) testrealm@host.com".to_owned()],
+        *events.lock().unwrap()
+    );
+}
+
+#[allow(deprecated)]
+#[test]
+fn test_reject_stale_nonce() {
+    let (validator, events) = validator_with_logger("testrealm@host.com");
+    let digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    validator.reject_stale_nonce(&digest);
+    assert_eq!(
+        vec!["stale dcd98b7102dd2f0e8b11d0f600bfb0c093".to_owned()],
+        *events.lock().unwrap()
+    );
+}
+
+fn rfc2617_ha1_lookup(username: &str, realm: &str) -> Option<String> {
+    if username == "Mufasa" && realm == "testrealm@host.com" {
+        Some("939e7578ed9e3c518a452acee763bce9".to_owned())
+    } else {
+        None
+    }
+}
+
+#[allow(deprecated)]
+#[test]
+fn test_validate_digest_using_ha1_fn() {
+    let digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    let result = validate_digest_using_ha1_fn(&digest, "GET", b"", rfc2617_ha1_lookup);
+    assert_eq!(Ok(()), result)
+}
+
+#[allow(deprecated)]
+#[test]
+fn test_validate_digest_using_ha1_fn_with_unknown_username() {
+    let digest = rfc7616_digest_header(HashAlgorithm::MD5, "8ca523f5e9506fed4657c9700eebdbec");
+    let result = validate_digest_using_ha1_fn(&digest, "GET", b"", rfc2617_ha1_lookup);
+    assert_eq!(Err(ValidationFailure::UnknownUsername), result)
+}
+
+fn anonymous_ha1_lookup(username: &str, realm: &str) -> Option<String> {
+    if username.is_empty() && realm == "testrealm@host.com" {
+        Some("282b4635ca377621790071ebb49f8a58".to_owned())
+    } else {
+        None
+    }
+}
+
+#[allow(deprecated)]
+#[test]
+fn test_validate_digest_using_ha1_fn_with_anonymous_digest() {
+    let mut digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    digest.username = Username::Plain(String::new());
+    digest.response = "b2c35e90ccad49d0f82d071816672491".to_owned();
+    let result = validate_digest_using_ha1_fn(&digest, "GET", b"", rfc2617_ha1_lookup);
+    assert_eq!(Err(ValidationFailure::AnonymousNotAllowed), result);
+
+    let result = validate_digest_using_ha1_fn(&digest, "GET", b"", anonymous_ha1_lookup);
+    assert_eq!(Ok(()), result);
+}
+
+#[allow(deprecated)]
+#[test]
+fn test_validate_digest_using_ha1_fn_with_response_mismatch() {
+    let digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    let result = validate_digest_using_ha1_fn(&digest, "POST", b"", rfc2617_ha1_lookup);
+    assert_eq!(Err(ValidationFailure::ResponseMismatch), result)
+}
+
+#[allow(deprecated)]
+#[test]
+fn test_validate_digest_using_ha1_fn_with_invalid_structure() {
+    let mut digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    digest.nonce_count = None;
+    let result = validate_digest_using_ha1_fn(&digest, "GET", b"", rfc2617_ha1_lookup);
+    assert_eq!(Err(ValidationFailure::StructureInvalid(DigestError::MissingNonceCount)), result);
+
+    // The structure is checked before the credentials are looked up.
+    let result = validate_digest_using_ha1_fn(&digest, "GET", b"", |_, _| None);
+    assert_eq!(Err(ValidationFailure::StructureInvalid(DigestError::MissingNonceCount)), result)
+}
+
+#[test]
+fn test_in_memory_credential_store_lookup() {
+    let mut store = InMemoryCredentialStore::new();
+    store.insert_password(
+        &HashAlgorithm::SHA256,
+        "Mufasa".to_owned(),
+        "http-auth@example.org".to_owned(),
+        "Circle of Life",
+    );
+    let digest = rfc7616_digest_header(
+        HashAlgorithm::SHA256,
+        "753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1",
+    );
+    let result = validate_digest_using_ha1_fn(&digest, "GET", b"", |username, realm| {
+        store.lookup_a1(username, realm)
+    });
+    assert_eq!(Ok(()), result);
+    assert_eq!(None, store.lookup_a1("Mufasa", "api@example.org"));
+    assert_eq!(None, store.lookup_a1("Simba", "http-auth@example.org"));
+}
+
+#[test]
+fn test_in_memory_credential_store_with_hashed_a1() {
+    let mut store = InMemoryCredentialStore::new();
+    store.insert_hashed_a1(
+        "Mufasa".to_owned(),
+        "testrealm@host.com".to_owned(),
+        "939e7578ed9e3c518a452acee763bce9".to_owned(),
+    );
+    assert_eq!(
+        Some("939e7578ed9e3c518a452acee763bce9".to_owned()),
+        store.lookup_a1("Mufasa", "testrealm@host.com")
+    );
+}
+
+#[test]
+fn test_in_memory_credential_store_debug_redacts_a1() {
+    let mut store = InMemoryCredentialStore::new();
+    store.insert_hashed_a1(
+        "Mufasa".to_owned(),
+        "testrealm@host.com".to_owned(),
+        "939e7578ed9e3c518a452acee763bce9".to_owned(),
+    );
+    let debug = format!("{:?}", store);
+    assert!(debug.contains("Mufasa"));
+    assert!(debug.contains("testrealm@host.com"));
+    assert!(!debug.contains("939e7578ed9e3c518a452acee763bce9"));
+}
+
+#[test]
+fn test_validate_client_nonce_uniqueness() {
+    let mut seen = HashSet::new();
+    assert!(validate_client_nonce_uniqueness("0a4f113b", &mut seen));
+    assert!(validate_client_nonce_uniqueness("f2/wE4q74E6zIJEtWaHKaf5wv", &mut seen));
+    assert!(!validate_client_nonce_uniqueness("0a4f113b", &mut seen));
+    assert_eq!(2, seen.len())
+}
+
+// The RFC 2617 example, for a URI with a percent-encoded tilde.
+#[allow(deprecated)]
+fn percent_encoded_uri_digest() -> Digest {
+    let mut digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    digest.request_uri = "/%7Emufasa/index.html".to_owned();
+    digest.response = digest
+        .using_password("GET", b"", "Circle Of Life".to_owned())
+        .expect("Could not generate response");
+    digest
+}
+
+#[allow(deprecated)]
+fn batch_requests() -> Vec<BatchRequest<'static>> {
+    let digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    let mut wrong_response = digest.clone();
+    wrong_response.response = "00000000000000000000000000000000".to_owned();
+    vec![
+        (digest.clone(), "GET", b"", "/dir/index.html".to_owned()),
+        (percent_encoded_uri_digest(), "GET", b"", "/~mufasa/index.html".to_owned()),
+        (wrong_response, "GET", b"", "/dir/index.html".to_owned()),
+        (digest.clone(), "POST", b"", "/dir/index.html".to_owned()),
+        (digest, "GET", b"", "/dir/other.html".to_owned()),
+    ]
+}
+
+fn expected_batch_results() -> Vec<Result<(), ValidationFailure>> {
+    vec![
+        Ok(()),
+        Ok(()),
+        Err(ValidationFailure::ResponseMismatch),
+        Err(ValidationFailure::ResponseMismatch),
+        Err(ValidationFailure::StructureInvalid(DigestError::UriMismatch {
+            digest_uri: "/dir/index.html".to_owned(),
+            actual_uri: "/dir/other.html".to_owned(),
+        })),
+    ]
+}
+
+#[test]
+fn test_validate_batch() {
+    let requests = batch_requests();
+    assert_eq!(expected_batch_results(), validate_batch(&requests, "Circle Of Life"));
+    assert!(validate_batch(&[], "Circle Of Life").is_empty());
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_validate_batch_parallel() {
+    let requests = batch_requests();
+    assert_eq!(expected_batch_results(), validate_batch_parallel(&requests, "Circle Of Life"));
+    assert_eq!(
+        validate_batch(&requests, "Circle of Death"),
+        validate_batch_parallel(&requests, "Circle of Death")
+    );
+}
+
+#[allow(deprecated)]
+#[test]
+fn test_validate_opaque() {
+    let opaque = "FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS".to_owned();
+    let mut challenge = rfc7616_challenge(HashAlgorithm::MD5);
+    let mut digest = rfc7616_digest_header(HashAlgorithm::MD5, "8ca523f5e9506fed4657c9700eebdbec");
+    assert_eq!(Ok(()), validate_opaque(&challenge, &digest));
+
+    digest.opaque = None;
+    assert_eq!(
+        Err(DigestError::OpaqueMismatch {
+            expected: Some(opaque.clone()),
+            got: None,
+        }),
+        validate_opaque(&challenge, &digest)
+    );
+
+    challenge.opaque = None;
+    assert_eq!(Ok(()), validate_opaque(&challenge, &digest));
+
+    digest.opaque = Some(opaque.clone());
+    assert_eq!(
+        Err(DigestError::OpaqueMismatch {
+            expected: None,
+            got: Some(opaque.clone()),
+        }),
+        validate_opaque(&challenge, &digest)
+    );
+
+    challenge.opaque = Some(opaque.to_lowercase());
+    assert_eq!(
+        Err(DigestError::OpaqueMismatch {
+            expected: Some(opaque.to_lowercase()),
+            got: Some(opaque),
+        }),
+        validate_opaque(&challenge, &digest)
+    );
+}
+
+#[allow(deprecated)]
+#[test]
+fn test_validate_domain() {
+    let mut challenge = rfc7616_challenge(HashAlgorithm::MD5);
+    let mut digest = rfc7616_digest_header(HashAlgorithm::MD5, "8ca523f5e9506fed4657c9700eebdbec");
+    assert_eq!(Ok(()), validate_domain(&challenge, &digest));
+
+    challenge.domain = vec!["/dir/".to_owned()];
+    assert_eq!(Ok(()), validate_domain(&challenge, &digest));
+
+    digest.request_uri = "/admin/index.html".to_owned();
+    assert_eq!(
+        Err(DigestError::UriOutsideDomain("/admin/index.html".to_owned())),
+        validate_domain(&challenge, &digest)
+    );
+}
+
+#[test]
+fn test_encode_and_decode_nonce() {
+    // See: RFC 7616, Section 3.9.1
+    let nonce = "7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v";
+    let bytes = decode_nonce(nonce).unwrap();
+    assert_eq!(33, bytes.len());
+    assert_eq!(nonce, encode_nonce(&bytes));
+    assert_eq!("7ypf_xlj9XXwfDPEoM4URrv_xwf94BcCAzFZH4GiTo0v", encode_nonce_url_safe(&bytes));
+}
+
+#[test]
+fn test_encode_nonce_with_padding() {
+    assert_eq!("AQI=", encode_nonce(&[1, 2]));
+    assert_eq!(Ok(vec![1, 2]), decode_nonce("AQI="));
+    assert_eq!("AQI", encode_nonce_url_safe(&[1, 2]));
+}
+
+#[test]
+fn test_decode_invalid_nonce() {
+    assert_eq!(Err(DigestError::InvalidNonceEncoding), decode_nonce("not base64!"));
+    assert_eq!(Err(DigestError::InvalidNonceEncoding), decode_nonce("AQI"));
+    assert_eq!(
+        Err(DigestError::InvalidNonceEncoding),
+        decode_nonce("7ypf_xlj9XXwfDPEoM4URrv_xwf94BcCAzFZH4GiTo0v")
+    );
+}
+
+const NONCE: &str = "dcd98b7102dd2f0e8b11d0f600bfb0c093";
+
+#[test]
+fn test_nonce_store_with_unknown_nonce() {
+    let mut store = InMemoryNonceStore::new();
+    assert_eq!(Err(ValidationFailure::UnknownNonce), store.record_use(NONCE, 1));
+    assert_eq!(None, store.max_nc_seen(NONCE));
+}
+
+#[test]
+fn test_nonce_store_rejects_reused_nonce_count() {
+    let mut store = InMemoryNonceStore::new();
+    store.insert_nonce(NONCE);
+    assert_eq!(None, store.max_nc_seen(NONCE));
+    assert_eq!(Ok(()), store.record_use(NONCE, 1));
+    assert_eq!(Err(ValidationFailure::NonceCountReused), store.record_use(NONCE, 1));
+    assert_eq!(Some(1), store.max_nc_seen(NONCE));
+}
+
+#[test]
+fn test_nonce_store_without_strict_ordering() {
+    let mut store = InMemoryNonceStore::new();
+    store.insert_nonce(NONCE);
+    assert_eq!(Ok(()), store.record_use(NONCE, 5));
+    assert_eq!(Ok(()), store.record_use(NONCE, 3));
+    assert_eq!(Some(5), store.max_nc_seen(NONCE));
+    assert_eq!(Err(ValidationFailure::NonceCountReused), store.record_use(NONCE, 3));
+}
+
+#[test]
+fn test_nonce_store_with_strict_ordering() {
+    let mut store = InMemoryNonceStore::new();
+    store.set_strict_ordering(true);
+    store.insert_nonce(NONCE);
+    for nc in 1..6 {
+        assert_eq!(Ok(()), store.record_use(NONCE, nc));
+    }
+    assert_eq!(Some(5), store.max_nc_seen(NONCE));
+    assert_eq!(
+        Err(ValidationFailure::NonceCountOutOfOrder { expected: 6, got: 3 }),
+        store.record_use(NONCE, 3)
+    );
+    assert_eq!(
+        Err(ValidationFailure::NonceCountOutOfOrder { expected: 6, got: 7 }),
+        store.record_use(NONCE, 7)
+    );
+    assert_eq!(Ok(()), store.record_use(NONCE, 6));
+}
+
+#[test]
+fn test_nonce_store_eviction() {
+    let now = Arc::new(AtomicU64::new(1000));
+    let clock = now.clone();
+    let mut store = InMemoryNonceStore::with_clock(Box::new(move || clock.load(Ordering::SeqCst)));
+    store.insert_nonce(NONCE);
+    assert_eq!(Ok(()), store.record_use(NONCE, 1));
+    now.store(1030, Ordering::SeqCst);
+    store.insert_nonce("newer");
+    now.store(1100, Ordering::SeqCst);
+
+    store.evict_older_than(100, now.load(Ordering::SeqCst));
+    assert_eq!(Ok(()), store.record_use(NONCE, 2));
+
+    store.evict_older_than(90, now.load(Ordering::SeqCst));
+    assert_eq!(Err(ValidationFailure::NonceExpired), store.record_use(NONCE, 3));
+    assert_eq!(None, store.max_nc_seen(NONCE));
+    assert_eq!(Ok(()), store.record_use("newer", 1));
+
+    store.evict_before_timestamp(1031);
+    assert_eq!(Err(ValidationFailure::NonceExpired), store.record_use("newer", 2));
+    assert_eq!(Err(ValidationFailure::UnknownNonce), store.record_use("never issued", 1));
+
+    store.insert_nonce("newer");
+    assert_eq!(Ok(()), store.record_use("newer", 1));
+    store.evict_before_timestamp(1101);
+    store.forget_expired_nonces();
+    assert_eq!(Err(ValidationFailure::UnknownNonce), store.record_use(NONCE, 3));
+    assert_eq!(Err(ValidationFailure::UnknownNonce), store.record_use("newer", 2));
+}
+
+#[allow(deprecated)]
+#[cfg(feature = "http")]
+#[test]
+fn test_validate_digest_for_request() {
+    let digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    let request = Request::get("/dir/index.html").body(Vec::new()).unwrap();
+    assert_eq!(Ok(()), validate_digest_for_request(&digest, &request, "Circle Of Life"));
+    assert_eq!(
+        Err(ValidationFailure::ResponseMismatch),
+        validate_digest_for_request(&digest, &request, "Circle of Death")
+    );
+}
+
+#[allow(deprecated)]
+#[cfg(feature = "http")]
+#[test]
+fn test_validate_digest_for_request_with_absolute_uri() {
+    let digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    let request = Request::get("http://www.nowhere.org/dir/index.html")
+        .body(Vec::new())
+        .unwrap();
+    assert_eq!(Ok(()), validate_digest_for_request(&digest, &request, "Circle Of Life"));
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_validate_digest_for_request_with_percent_encoded_uri() {
+    let digest = percent_encoded_uri_digest();
+    let request = Request::get("/~mufasa/index.html").body(Vec::new()).unwrap();
+    assert_eq!(Ok(()), validate_digest_for_request(&digest, &request, "Circle Of Life"));
+}
+
+#[allow(deprecated)]
+#[cfg(feature = "http")]
+#[test]
+fn test_validate_digest_for_request_with_mismatched_method_or_uri() {
+    let digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    let request = Request::post("/dir/index.html").body(Vec::new()).unwrap();
+    assert_eq!(
+        Err(ValidationFailure::ResponseMismatch),
+        validate_digest_for_request(&digest, &request, "Circle Of Life")
+    );
+    let request = Request::get("/dir/other.html").body(Vec::new()).unwrap();
+    let uri_mismatch = DigestError::UriMismatch {
+        digest_uri: "/dir/index.html".to_owned(),
+        actual_uri: "/dir/other.html".to_owned(),
+    };
+    assert_eq!(
+        Err(ValidationFailure::StructureInvalid(uri_mismatch)),
+        validate_digest_for_request(&digest, &request, "Circle Of Life")
+    );
+}
+
+#[test]
+fn test_display_validation_failure() {
+    assert_eq!(
+        "Digest realm does not match",
+        format!("{}", ValidationFailure::RealmMismatch)
+    );
+    assert_eq!(
+        "Cannot generate digest: Missing required field: cnonce",
+        format!("{}", ValidationFailure::MalformedDigest(DigestError::MissingClientNonce))
+    );
+    assert_eq!(
+        "Digest response does not match",
+        format!("{}", ValidationFailure::ResponseMismatch)
+    );
+    assert_eq!(
+        "Digest username is unknown",
+        format!("{}", ValidationFailure::UnknownUsername)
+    );
+    assert_eq!(
+        "Digest nonce count is out of order: expected 00000006, got 00000003",
+        format!("{}", ValidationFailure::NonceCountOutOfOrder { expected: 6, got: 3 })
+    );
+    assert_eq!(
+        "Digest nonce count was already used",
+        format!("{}", ValidationFailure::NonceCountReused)
+    );
+    assert_eq!(
+        "Anonymous access is not allowed",
+        format!("{}", ValidationFailure::AnonymousNotAllowed)
+    );
+    assert_eq!("Digest nonce is unknown", format!("{}", ValidationFailure::UnknownNonce));
+    assert_eq!("Digest nonce has expired", format!("{}", ValidationFailure::NonceExpired));
+}
+
+fn realm_router() -> RealmRouter {
+    let mut api = InMemoryCredentialStore::new();
+    api.insert_hashed_a1("Mufasa".to_owned(), "API Realm".to_owned(), "api".to_owned());
+    let mut admin = InMemoryCredentialStore::new();
+    admin.insert_hashed_a1("Mufasa".to_owned(), "Admin Realm".to_owned(), "admin".to_owned());
+    let mut root = InMemoryCredentialStore::new();
+    root.insert_hashed_a1("Mufasa".to_owned(), "Root Realm".to_owned(), "root".to_owned());
+
+    let mut router = RealmRouter::new();
+    router.add_realm("/", "Root Realm".to_owned(), Box::new(root));
+    router.add_realm("/api", "API Realm".to_owned(), Box::new(api));
+    router.add_realm("/admin", "Admin Realm".to_owned(), Box::new(admin));
+    router
+}
+
+#[allow(deprecated)]
+#[test]
+fn test_realm_router_route() {
+    let router = realm_router();
+    let mut digest = rfc2617_digest_header(HashAlgorithm::MD5);
+
+    digest.realm = "Admin Realm".to_owned();
+    let store = router.route("/admin/users", &digest).expect("Could not route");
+    assert_eq!(Some("admin".to_owned()), store.lookup_a1("Mufasa", "Admin Realm"));
+    assert!(router.route("/api/v1/data", &digest).is_none());
+
+    digest.realm = "API Realm".to_owned();
+    let store = router.route("/api/v1/data", &digest).expect("Could not route");
+    assert_eq!(Some("api".to_owned()), store.lookup_a1("Mufasa", "API Realm"));
+    let store = router.route("/api", &digest).expect("Could not route");
+    assert_eq!(Some("api".to_owned()), store.lookup_a1("Mufasa", "API Realm"));
+
+    digest.realm = "Root Realm".to_owned();
+    let store = router.route("/apiary", &digest).expect("Could not route");
+    assert_eq!(Some("root".to_owned()), store.lookup_a1("Mufasa", "Root Realm"));
+}
+
+#[allow(deprecated)]
+#[test]
+fn test_realm_router_route_with_dot_segments() {
+    let router = realm_router();
+    let mut digest = rfc2617_digest_header(HashAlgorithm::MD5);
+
+    digest.realm = "API Realm".to_owned();
+    assert!(router.route("/api/../admin/users", &digest).is_none());
+    assert!(router.route("/api/%2E%2E/admin", &digest).is_none());
+    assert!(router.route("/api/%2e%2e/admin", &digest).is_none());
+
+    digest.realm = "Admin Realm".to_owned();
+    let store = router.route("/api/../admin/users", &digest).expect("Could not route");
+    assert_eq!(Some("admin".to_owned()), store.lookup_a1("Mufasa", "Admin Realm"));
+    let store = router.route("/api/%2E%2E/admin", &digest).expect("Could not route");
+    assert_eq!(Some("admin".to_owned()), store.lookup_a1("Mufasa", "Admin Realm"));
+
+    let challenge = router
+        .generate_challenge("/api/%2E%2E/admin", "nonce".to_owned())
+        .expect("Could not generate challenge");
+    assert_eq!("Admin Realm", challenge.realm);
+}
+
+#[allow(deprecated)]
+#[test]
+fn test_realm_router_without_matching_prefix() {
+    let mut router = RealmRouter::new();
+    router.add_realm("/api", "API Realm".to_owned(), Box::new(InMemoryCredentialStore::new()));
+    let mut digest = rfc2617_digest_header(HashAlgorithm::MD5);
+    digest.realm = "API Realm".to_owned();
+
+    assert!(router.route("/admin", &digest).is_none());
+    assert!(router.generate_challenge("/admin", "nonce".to_owned()).is_none());
+}
+
+#[test]
+fn test_realm_router_generate_challenge() {
+    let router = realm_router();
+    let challenge = router
+        .generate_challenge("/admin/users", "nonce".to_owned())
+        .expect("Could not generate challenge");
+
+    assert_eq!("Admin Realm", challenge.realm);
+    assert_eq!(vec!["/admin".to_owned()], challenge.domain);
+    assert_eq!("nonce", challenge.nonce);
+    assert_eq!(HashAlgorithm::SHA256, challenge.algorithm);
+    assert_eq!(vec![Qop::Auth], challenge.qop);
+}
+
+const SERVER_NONCE: &str = "000000006553f100\
+                            f86ef80bf36f00030886cb6c55cd05f525812b1567963dd772721b0e04a2bbc3";
+
+#[test]
+fn test_generate_server_nonce() {
+    assert_eq!(SERVER_NONCE, generate_server_nonce(b"secret", 1_700_000_000));
+    assert!(SERVER_NONCE != generate_server_nonce(b"other secret", 1_700_000_000));
+    assert!(SERVER_NONCE != generate_server_nonce(b"secret", 1_700_000_001));
+}
+
+#[test]
+fn test_generate_401_response() {
+    let response = generate_401_response(
+        "http-auth@example.org",
+        b"secret",
+        1_700_000_000,
+        HashAlgorithm::SHA512256,
+        vec![Qop::Auth, Qop::AuthInt],
+    );
+    let (name, value) = response.expect("Could not generate response");
+    assert_eq!("WWW-Authenticate", name);
+
+    let challenge: Challenge = value.parse().expect("Could not parse challenge");
+    assert_eq!("http-auth@example.org", challenge.realm);
+    assert!(challenge.domain.is_empty());
+    assert_eq!(SERVER_NONCE, challenge.nonce);
+    assert_eq!(None, challenge.opaque);
+    assert!(!challenge.stale);
+    assert_eq!(HashAlgorithm::SHA512256, challenge.algorithm);
+    assert_eq!(vec![Qop::Auth, Qop::AuthInt], challenge.qop);
+    assert_eq!(None, challenge.charset);
+    assert!(!challenge.userhash);
+}
+
+#[test]
+fn test_generate_401_response_with_crlf_in_realm() {
+    let realm = "realm\r\nX-Injected: 1";
+    assert_eq!(
+        Err(DigestError::CrlfInjection("realm")),
+        generate_401_response(realm, b"secret", 0, HashAlgorithm::SHA256, vec![])
+    );
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_generate_401_http_response() {
+    let response = generate_401_http_response(
+        "http-auth@example.org",
+        b"secret",
+        1_700_000_000,
+        HashAlgorithm::SHA256,
+        vec![Qop::Auth],
+    );
+    let response = response.expect("Could not generate response");
+    assert_eq!(http::StatusCode::UNAUTHORIZED, response.status());
+
+    let value = response.headers()["WWW-Authenticate"].to_str().unwrap();
+    let challenge: Challenge = value.parse().expect("Could not parse challenge");
+    assert_eq!("http-auth@example.org", challenge.realm);
+    assert_eq!(SERVER_NONCE, challenge.nonce);
+    assert_eq!(HashAlgorithm::SHA256, challenge.algorithm);
+    assert_eq!(vec![Qop::Auth], challenge.qop);
+}
@@ -0,0 +1,147 @@
+// Copyright (c) 2017 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! [wasm-bindgen](https://rustwasm.github.io/wasm-bindgen/) bindings, for computing and validating
+//! `Digest` responses in JavaScript.
+//!
+//! Only the `core` module is used, so this can be built without the `std` feature, e.g. via
+//! `wasm-pack build -- --no-default-features --features wasm`.
+
+use alloc::format;
+use alloc::string::String;
+use super::core::{HashAlgorithm, generate_kd, generate_simple_hashed_a1, hash_value};
+use wasm_bindgen::prelude::*;
+
+mod test;
+
+struct Parameters<'a> {
+    username: &'a str,
+    realm: &'a str,
+    nonce: &'a str,
+    nonce_count: &'a str,
+    client_nonce: &'a str,
+    qop: &'a str,
+    method: &'a str,
+    uri: &'a str,
+    password: &'a str,
+    algorithm: &'a str,
+}
+
+impl<'a> Parameters<'a> {
+    fn response(&self) -> Result<String, &'static str> {
+        let algorithm = HashAlgorithm::from_name(self.algorithm).ok_or("Invalid algorithm")?;
+        let mut hashed_a1 = generate_simple_hashed_a1(
+            &algorithm,
+            self.username.as_bytes(),
+            self.realm,
+            self.password,
+        );
+        if algorithm.is_session() {
+            if self.client_nonce.is_empty() {
+                return Err("Session algorithms require a client nonce");
+            }
+            let data = format!("{}:{}", self.nonce, self.client_nonce);
+            hashed_a1 = generate_kd(&algorithm, &hashed_a1, &data);
+        }
+        let hashed_a2 = hash_value(&algorithm, format!("{}:{}", self.method, self.uri).as_bytes());
+        let data = match self.qop {
+            "" => format!("{}:{}", self.nonce, hashed_a2),
+            "auth" => {
+                format!(
+                    "{}:{}:{}:{}:{}",
+                    self.nonce,
+                    self.nonce_count,
+                    self.client_nonce,
+                    self.qop,
+                    hashed_a2
+                )
+            }
+            "auth-int" => return Err("The auth-int qop is not supported"),
+            _ => return Err("Invalid qop value"),
+        };
+        Ok(generate_kd(&algorithm, &hashed_a1, &data))
+    }
+}
+
+/// Generates the `response` parameter of a `Digest` header. An empty `qop` means RFC 2069
+/// compatibility mode, in which case `nc` and `cnonce` are ignored.
+///
+/// Throws an error if `algorithm` or `qop` are invalid, if `qop` is `auth-int` (which requires the
+/// entity body), or if a session algorithm is used without a `cnonce`.
+#[allow(clippy::too_many_arguments)]
+#[wasm_bindgen]
+pub fn generate_digest_response(
+    username: &str,
+    realm: &str,
+    nonce: &str,
+    nc: &str,
+    cnonce: &str,
+    qop: &str,
+    method: &str,
+    uri: &str,
+    password: &str,
+    algorithm: &str,
+) -> Result<String, JsError> {
+    let parameters = Parameters {
+        username,
+        realm,
+        nonce,
+        nonce_count: nc,
+        client_nonce: cnonce,
+        qop,
+        method,
+        uri,
+        password,
+        algorithm,
+    };
+    parameters.response().map_err(JsError::new)
+}
+
+/// Validates the `response` parameter of a `Digest` header, given the password. Returns `false`
+/// for any input that `generate_digest_response` would reject.
+#[allow(clippy::too_many_arguments)]
+#[wasm_bindgen]
+pub fn validate_digest_response(
+    username: &str,
+    realm: &str,
+    nonce: &str,
+    nc: &str,
+    cnonce: &str,
+    qop: &str,
+    method: &str,
+    uri: &str,
+    password: &str,
+    algorithm: &str,
+    response: &str,
+) -> bool {
+    let parameters = Parameters {
+        username,
+        realm,
+        nonce,
+        nonce_count: nc,
+        client_nonce: cnonce,
+        qop,
+        method,
+        uri,
+        password,
+        algorithm,
+    };
+    parameters.response().is_ok_and(|expected| expected == response)
+}
@@ -0,0 +1,139 @@
+// Copyright (c) 2017 Mark Lee
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+#![cfg(test)]
+
+use super::{Parameters, generate_digest_response, validate_digest_response};
+
+fn rfc2617_parameters() -> Parameters<'static> {
+    Parameters {
+        username: "Mufasa",
+        realm: "testrealm@host.com",
+        nonce: "dcd98b7102dd2f0e8b11d0f600bfb0c093",
+        nonce_count: "00000001",
+        client_nonce: "0a4f113b",
+        qop: "auth",
+        method: "GET",
+        uri: "/dir/index.html",
+        password: "Circle Of Life",
+        algorithm: "MD5",
+    }
+}
+
+#[test]
+fn test_generate_digest_response() {
+    let response = generate_digest_response(
+        "Mufasa",
+        "testrealm@host.com",
+        "dcd98b7102dd2f0e8b11d0f600bfb0c093",
+        "00000001",
+        "0a4f113b",
+        "auth",
+        "GET",
+        "/dir/index.html",
+        "Circle Of Life",
+        "MD5",
+    );
+    assert_eq!("6629fae49393a05397450978507c4ef1", response.unwrap());
+}
+
+#[test]
+fn test_generate_digest_response_with_sha256() {
+    let response = generate_digest_response(
+        "Mufasa",
+        "http-auth@example.org",
+        "7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v",
+        "00000001",
+        "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ",
+        "auth",
+        "GET",
+        "/dir/index.html",
+        "Circle of Life",
+        "SHA-256",
+    );
+    assert_eq!(
+        "753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1",
+        response.unwrap()
+    );
+}
+
+#[test]
+fn test_response_without_qop() {
+    let parameters = Parameters {
+        qop: "",
+        nonce_count: "",
+        client_nonce: "",
+        ..rfc2617_parameters()
+    };
+    assert_eq!(
+        "670fd8c2df070c60b045671b8b24ff02",
+        parameters.response().unwrap()
+    );
+}
+
+#[test]
+fn test_response_with_session_algorithm() {
+    let parameters = Parameters { algorithm: "MD5-sess", ..rfc2617_parameters() };
+    assert!(parameters.response().is_ok());
+    let parameters = Parameters { client_nonce: "", ..parameters };
+    assert!(parameters.response().is_err());
+}
+
+#[test]
+fn test_response_with_invalid_parameters() {
+    let parameters = Parameters { algorithm: "SHA-1", ..rfc2617_parameters() };
+    assert!(parameters.response().is_err());
+    let parameters = Parameters { qop: "auth-int", ..rfc2617_parameters() };
+    assert!(parameters.response().is_err());
+    let parameters = Parameters { qop: "invalid", ..rfc2617_parameters() };
+    assert!(parameters.response().is_err());
+}
+
+#[test]
+fn test_validate_digest_response() {
+    let valid = validate_digest_response(
+        "Mufasa",
+        "testrealm@host.com",
+        "dcd98b7102dd2f0e8b11d0f600bfb0c093",
+        "00000001",
+        "0a4f113b",
+        "auth",
+        "GET",
+        "/dir/index.html",
+        "Circle Of Life",
+        "MD5",
+        "6629fae49393a05397450978507c4ef1",
+    );
+    assert!(valid);
+    let invalid = validate_digest_response(
+        "Mufasa",
+        "testrealm@host.com",
+        "dcd98b7102dd2f0e8b11d0f600bfb0c093",
+        "00000001",
+        "0a4f113b",
+        "auth",
+        "GET",
+        "/dir/index.html",
+        "Circle of Life",
+        "MD5",
+        "6629fae49393a05397450978507c4ef1",
+    );
+    assert!(!invalid);
+}
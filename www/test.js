@@ -0,0 +1,28 @@
+// Tests the WebAssembly bindings against the example in RFC 2617, section 3.5.
+// Build the package first via `npm run build`.
+
+'use strict'
+
+const assert = require('assert')
+const guardhaus = require('../pkg/guardhaus.js')
+
+const parameters = [
+  'Mufasa',
+  'testrealm@host.com',
+  'dcd98b7102dd2f0e8b11d0f600bfb0c093',
+  '00000001',
+  '0a4f113b',
+  'auth',
+  'GET',
+  '/dir/index.html',
+  'Circle Of Life',
+  'MD5'
+]
+const expected = '6629fae49393a05397450978507c4ef1'
+
+assert.strictEqual(guardhaus.generate_digest_response(...parameters), expected)
+assert.ok(guardhaus.validate_digest_response(...parameters, expected))
+assert.ok(!guardhaus.validate_digest_response(...parameters, '0'.repeat(32)))
+assert.throws(() => guardhaus.generate_digest_response(...parameters.slice(0, 9), 'SHA-1'))
+
+console.log('ok')